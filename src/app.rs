@@ -1,11 +1,34 @@
-use crate::radiooo::{self, CountryForDecade};
-use libmpv2::Mpv;
-use log::{debug, error};
+use crate::availability::{self, Availability, AvailabilityCache};
+use crate::cover::{Cover, CoverError, CoverStore, GraphicsProtocol};
+use crate::daemon::{DaemonClient, DaemonError, Status};
+use crate::download::{AudioFormat, DownloadError, Downloader};
+use crate::event::{Control, Event};
+use crate::filter::Filter;
+use crate::keymap::Keymap;
+use crate::library::Library;
+use crate::player::Player;
+use crate::radiooo::{
+    self, CountryCode, Decade, ExploreRequest, Mood, PlayMode, RadioooClient, Track,
+};
+use crate::scrobble::{self, Scrobbler};
+use crate::ui::Areas;
+use log::{error, info};
 use ratatui::widgets::*;
-use std::collections::HashMap;
-use std::{default, error, panic};
-const MAX_VOLUME: u16 = 150;
-const VOLUME_INCREMENT: u16 = 5;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{error, fmt, io};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::{runtime::Handle, task};
+/// Seconds skipped by a seek.
+pub const SEEK_STEP: f64 = 10.0;
+/// Number of tracks kept in the playback history.
+pub const HISTORY_LEN: usize = 100;
+/// Time a notice stays on screen.
+pub const NOTICE_TTL: Duration = Duration::from_secs(5);
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
@@ -17,6 +40,55 @@ pub enum PlayState {
     Stopped,
 }
 
+impl PlayState {
+    /// Track currently loaded, whether it is playing or paused.
+    pub fn track(&self) -> Option<&Track> {
+        match self {
+            PlayState::Paused(track) | PlayState::Playing(track) => Some(track),
+            PlayState::Stopped => None,
+        }
+    }
+}
+
+/// What a track requested in the background is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackRequest {
+    /// Next track of the station.
+    Next,
+    /// Track at this index of the history, with fresh links.
+    Previous(usize),
+    /// A given track with fresh links, e.g. a favorite.
+    Play,
+}
+
+/// Outcome of a request run in the background, delivered as an
+/// [`Event::Response`].
+#[derive(Debug)]
+pub enum Response {
+    /// Only the response to the latest track request is applied.
+    Track {
+        id: u64,
+        request: TrackRequest,
+        result: radiooo::Result<Box<Track>>,
+    },
+    Availability(radiooo::Result<Availability>),
+    Cover {
+        uuid: String,
+        result: Result<Cover, CoverError>,
+    },
+    /// Status returned by the daemon in remote mode.
+    Daemon(Result<Box<Status>, DaemonError>),
+}
+
+/// What the body of the interface shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum View {
+    /// The moods, decades and countries lists.
+    #[default]
+    Browse,
+    Favorites,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectedList {
     Country,
@@ -32,24 +104,120 @@ pub struct App {
 
     /// volume
     pub volume: u16,
+    pub max_volume: u16,
+    /// Step of the volume keys.
+    pub volume_increment: u16,
     pub muted: bool,
     pub play_state: PlayState,
+    /// Position and duration of the current track, polled on every tick.
+    pub position: Option<Duration>,
+    /// Number of seeks so far, signaled over MPRIS.
+    pub seeks: u64,
+    pub duration: Option<Duration>,
 
     pub decade_state: ListState,
     pub mood_state: ListState,
     pub country_state: ListState,
 
     pub list_selected: SelectedList,
+    /// Where the last frame drew what the mouse acts on.
+    pub areas: Areas,
+    /// Filter of the focused list, keys are typed in its query while set.
+    pub filter: Option<Filter>,
+    pub view: View,
+    /// Whether the help popup covers the view.
+    pub show_help: bool,
+    pub keymap: Keymap,
+    pub favorites_state: ListState,
+
+    /// Entries marked in each list, drawn from together.
+    pub marked_moods: BTreeSet<Mood>,
+    pub marked_decades: BTreeSet<Decade>,
+    pub marked_countries: BTreeSet<CountryCode>,
 
     pub current_setting: String,
-    pub country_availables: HashMap<i32, radiooo::CountryForDecade>,
+    /// Mode used to draw the tracks of the selection.
+    pub mode: PlayMode,
+    /// Island played from in [`PlayMode::Islands`].
+    pub island: Option<String>,
+    /// Selection the taxi playback draws its tracks from.
+    pub station: Option<ExploreRequest>,
+    /// Tracks played during this session, oldest first.
+    pub history: VecDeque<Track>,
+    /// Position of the current track in the history.
+    pub history_cursor: Option<usize>,
+    /// Last API failure, shown to the user instead of crashing.
+    pub error: Option<String>,
+    /// Outcome of the last background job, e.g. a finished download, and
+    /// when it happened.
+    pub notice: Option<(String, Instant)>,
+    pub country_availables: HashMap<Decade, radiooo::CountryForDecade>,
+    /// Where the availability is kept between launches.
+    pub availability_cache: Option<AvailabilityCache>,
+    /// Fetch of the availability running in the background.
+    pub availability_request: Option<JoinHandle<()>>,
+
+    pub player: Box<dyn Player>,
+    pub client: RadioooClient,
+    /// Stream loaded in the player.
+    pub format: AudioFormat,
+
+    /// Where covers are fetched from, covers are not shown without it.
+    pub covers: Option<CoverStore>,
+    /// Cover of the current track.
+    pub cover: Option<Cover>,
+    pub graphics: GraphicsProtocol,
+
+    /// Liked tracks, nothing can be liked without it.
+    pub library: Option<Library>,
+
+    /// Saves tracks for offline listening, nothing can be saved without it.
+    pub downloader: Option<Downloader>,
+    /// Downloads in progress, with the title of their track.
+    pub downloads: Vec<(String, JoinHandle<Result<PathBuf, DownloadError>>)>,
+
+    /// Daemon playing the music when the interface is only a client of it.
+    pub remote: Option<DaemonClient>,
+    /// A call to the daemon is in flight, the ticks wait for it before
+    /// polling the status again.
+    syncing: bool,
+
+    /// Where the responses of the API requests are sent, they are awaited
+    /// in place without it, e.g. by the daemon.
+    pub events: Option<UnboundedSender<Event>>,
+    /// Track request in flight and when it was sent, a newer one aborts it.
+    pub tuning: Option<(JoinHandle<()>, Instant)>,
+    /// Id of the latest track request.
+    track_request: u64,
 
-    pub mpv: Mpv,
+    /// Where the played tracks are submitted, nothing is without it.
+    pub scrobbler: Option<Scrobbler>,
+    /// When the current track started, until it is scrobbled.
+    pub listened_at: Option<u64>,
+    /// Time the current track was actually played, seeks excluded.
+    played: Duration,
+    /// Position read at the last tick, and when.
+    last_progress: Option<(Duration, Instant)>,
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
-    pub fn new(mpv: Mpv) -> Self {
+    pub fn new(player: Box<dyn Player>, client: RadioooClient) -> Self {
+        let mut app = Self::with_player(player, client);
+        app.apply_volume();
+        app
+    }
+
+    /// Constructs an [`App`] controlling the daemon behind `daemon`, which
+    /// keeps its own station, history and volume.
+    pub fn remote(daemon: DaemonClient, client: RadioooClient) -> Self {
+        let mut app = Self::with_player(Box::new(daemon.clone()), client);
+        app.remote = Some(daemon);
+        app.call_daemon("status", Value::Null);
+        app
+    }
+
+    fn with_player(player: Box<dyn Player>, client: RadioooClient) -> Self {
         let mut decade_state = ListState::default();
         decade_state.select(Some(0));
         let mut mood_state = ListState::default();
@@ -58,84 +226,1327 @@ impl App {
         country_state.select(Some(0));
         Self {
             volume: 50,
+            max_volume: 150,
+            volume_increment: 5,
             running: true,
             muted: false,
             play_state: PlayState::Stopped,
+            position: None,
+            seeks: 0,
+            duration: None,
             decade_state,
             mood_state,
             country_state,
             list_selected: SelectedList::Decade,
+            areas: Areas::default(),
+            filter: None,
+            view: View::default(),
+            show_help: false,
+            keymap: Keymap::default(),
+            favorites_state: ListState::default(),
+            marked_moods: BTreeSet::new(),
+            marked_decades: BTreeSet::new(),
+            marked_countries: BTreeSet::new(),
             current_setting: String::from(""),
+            mode: PlayMode::default(),
+            island: None,
+            station: None,
+            history: VecDeque::new(),
+            history_cursor: None,
+            error: None,
+            notice: None,
             country_availables: HashMap::new(),
-            mpv: mpv,
+            availability_cache: None,
+            availability_request: None,
+            player,
+            client,
+            format: AudioFormat::default(),
+            covers: None,
+            cover: None,
+            graphics: GraphicsProtocol::default(),
+            library: None,
+            downloader: None,
+            downloads: Vec::new(),
+            remote: None,
+            syncing: false,
+            events: None,
+            tuning: None,
+            track_request: 0,
+            scrobbler: None,
+            listened_at: None,
+            played: Duration::ZERO,
+            last_progress: None,
         }
     }
 
     /// Handles the tick event of the terminal.
-    pub fn tick(&self) {}
+    pub fn tick(&mut self) {
+        if self.remote.is_none() {
+            self.poll_progress();
+        } else if !self.syncing {
+            self.call_daemon("status", Value::Null);
+        }
+        self.poll_downloads();
+        if self
+            .notice
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= NOTICE_TTL)
+        {
+            self.notice = None;
+        }
+    }
+
+    /// Reads the playback position from the player.
+    fn poll_progress(&mut self) {
+        match self.play_state.track() {
+            Some(track) => {
+                let length = Duration::from_secs(track.length.into());
+                self.position = self.player.position();
+                self.duration = self.player.duration().or(Some(length));
+            }
+            None => {
+                self.position = None;
+                self.duration = None;
+            }
+        }
+        self.poll_scrobble();
+    }
+
+    /// Submits the current track once enough of it was played.
+    ///
+    /// The position only counts as played up to the time elapsed since the
+    /// last tick, so that seeking forward does not scrobble.
+    fn poll_scrobble(&mut self) {
+        let now = Instant::now();
+        if let (Some(position), Some((last, at))) = (self.position, self.last_progress) {
+            if position > last {
+                self.played += (position - last).min(now - at);
+            }
+        }
+        self.last_progress = self.position.map(|position| (position, now));
+        let (Some(scrobbler), Some(track), Some(duration)) =
+            (&self.scrobbler, self.play_state.track(), self.duration)
+        else {
+            return;
+        };
+        if self.played < scrobble::threshold(duration) {
+            return;
+        }
+        let Some(listened_at) = self.listened_at.take() else {
+            return;
+        };
+        let (scrobbler, track) = (scrobbler.clone(), track.clone());
+        tokio::spawn(async move {
+            if let Err(err) = scrobbler.scrobble(&track, listened_at).await {
+                error!("failed to scrobble {}, queued: {}", track._id, err);
+            }
+        });
+    }
+
+    /// Announces `track` as playing now, sending the listens still queued.
+    fn announce(&self, track: &Track) {
+        let Some(scrobbler) = self.scrobbler.clone() else {
+            return;
+        };
+        let track = track.clone();
+        tokio::spawn(async move {
+            if let Err(err) = scrobbler.flush().await {
+                error!("failed to send the queued listens: {}", err);
+            }
+            if let Err(err) = scrobbler.now_playing(&track).await {
+                error!("failed to announce {}: {}", track._id, err);
+            }
+        });
+    }
+
+    /// Seeks `offset` seconds in the current track, backward if negative.
+    pub fn seek(&mut self, offset: f64) {
+        if self.play_state.track().is_none() {
+            return;
+        }
+        if let Err(err) = self.player.seek(offset) {
+            self.report_error(err);
+            return;
+        }
+        self.seeks += 1;
+        // mpv seeks asynchronously, show the expected position right away
+        if let Some(position) = self.position {
+            let secs = (position.as_secs_f64() + offset).max(0.0);
+            let secs = self.duration.map_or(secs, |d| secs.min(d.as_secs_f64()));
+            self.position = Some(Duration::from_secs_f64(secs));
+        }
+    }
+
+    /// Moves to `position` seconds from the start of the current track.
+    pub fn set_position(&mut self, position: f64) {
+        if self.play_state.track().is_none() {
+            return;
+        }
+        if let Err(err) = self.player.seek_to(position) {
+            self.report_error(err);
+            return;
+        }
+        self.seeks += 1;
+        let secs = position.max(0.0);
+        let secs = self.duration.map_or(secs, |d| secs.min(d.as_secs_f64()));
+        self.position = Some(Duration::from_secs_f64(secs));
+    }
 
     /// Set running to false to quit the application.
     pub fn quit(&mut self) {
         self.running = false;
     }
-    pub fn get_countries_available(&mut self) -> Vec<String> {
-        let decade = radiooo::DECADES
-            .get(self.decade_state.selected().unwrap_or(0))
-            .unwrap();
-        let mood = radiooo::MOODS
-            .get(self.mood_state.selected().unwrap_or(0))
-            .unwrap();
-        let mut av = self
-            .country_availables
-            .get(decade)
-            .expect("should find decade")
-            .to_hash_map()
-            .get(*mood)
-            .expect("should find the hash map here")
-            .to_vec();
-        av.sort();
-        return av.clone();
+    /// Mood under the cursor in the Moods list.
+    pub fn selected_mood(&self) -> Mood {
+        Mood::ALL[self
+            .mood_state
+            .selected()
+            .unwrap_or(0)
+            .min(Mood::ALL.len() - 1)]
+    }
+
+    /// Decade under the cursor in the Decades list.
+    pub fn selected_decade(&self) -> Decade {
+        Decade::ALL[self
+            .decade_state
+            .selected()
+            .unwrap_or(0)
+            .min(Decade::ALL.len() - 1)]
     }
 
+    /// Country under the cursor in the Countries list, if any is available.
+    pub fn selected_country(&self) -> Option<CountryCode> {
+        self.get_countries_available()
+            .get(self.country_state.selected().unwrap_or(0))
+            .copied()
+    }
+
+    /// Moves the cursors to the given mood, decade and country.
+    ///
+    /// The country is looked up among the available ones, so the
+    /// availabilities should be populated first.
+    pub fn select(
+        &mut self,
+        mood: Option<Mood>,
+        decade: Option<Decade>,
+        country: Option<CountryCode>,
+    ) {
+        if let Some(mood) = mood {
+            self.mood_state
+                .select(Mood::ALL.iter().position(|m| *m == mood));
+        }
+        if let Some(decade) = decade {
+            self.decade_state
+                .select(Decade::ALL.iter().position(|d| *d == decade));
+        }
+        if let Some(country) = country {
+            if let Some(index) = self
+                .get_countries_available()
+                .iter()
+                .position(|c| *c == country)
+            {
+                self.country_state.select(Some(index));
+            }
+        }
+    }
+
+    /// Moods to draw from: the marked ones, or the one under the cursor.
+    pub fn moods(&self) -> Vec<Mood> {
+        if self.marked_moods.is_empty() {
+            vec![self.selected_mood()]
+        } else {
+            self.marked_moods.iter().copied().collect()
+        }
+    }
+
+    /// Decades to draw from: the marked ones, or the one under the cursor.
+    pub fn decades(&self) -> Vec<Decade> {
+        if self.marked_decades.is_empty() {
+            vec![self.selected_decade()]
+        } else {
+            self.marked_decades.iter().copied().collect()
+        }
+    }
+
+    /// Countries to draw from: the marked ones still available, or the one
+    /// under the cursor.
+    pub fn countries(&self) -> Vec<CountryCode> {
+        let available = self.get_countries_available();
+        let marked: Vec<CountryCode> = available
+            .iter()
+            .filter(|country| self.marked_countries.contains(country))
+            .copied()
+            .collect();
+        if marked.is_empty() {
+            self.selected_country().into_iter().collect()
+        } else {
+            marked
+        }
+    }
+
+    /// Request matching the current selection, if any country is available.
+    pub fn selection(&self) -> Option<ExploreRequest> {
+        let countries = self.countries();
+        if countries.is_empty() {
+            return None;
+        }
+        let request = ExploreRequest::new(self.moods(), self.decades(), countries).mode(self.mode);
+        match (&self.island, self.mode) {
+            (Some(island), PlayMode::Islands) => Some(request.island(island)),
+            _ => Some(request),
+        }
+    }
+
+    /// Switches to the next play mode, islands are only offered once an
+    /// island was chosen.
+    pub fn cycle_mode(&mut self) {
+        let modes: Vec<PlayMode> = PlayMode::ALL
+            .into_iter()
+            .filter(|mode| *mode != PlayMode::Islands || self.island.is_some())
+            .collect();
+        let current = modes.iter().position(|mode| *mode == self.mode);
+        self.mode = modes[current.map_or(0, |i| (i + 1) % modes.len())];
+    }
+
+    /// Countries having tracks for at least one of the selected moods and
+    /// decades, sorted by name.
+    pub fn get_countries_available(&self) -> Vec<CountryCode> {
+        let moods = self.moods();
+        let mut av: Vec<CountryCode> = self
+            .decades()
+            .iter()
+            .filter_map(|decade| self.country_availables.get(decade))
+            .flat_map(|ca| moods.iter().flat_map(|mood| ca.get(*mood)))
+            .copied()
+            .collect();
+        av.sort_by_key(|country| country.name());
+        av.dedup();
+        av
+    }
+
+    /// Marks or unmarks the entry under the cursor of the focused list.
+    pub fn toggle_mark(&mut self) {
+        fn toggle<T: Ord>(set: &mut BTreeSet<T>, value: T) {
+            if !set.remove(&value) {
+                set.insert(value);
+            }
+        }
+        match self.list_selected {
+            SelectedList::Mood => {
+                let mood = self.selected_mood();
+                toggle(&mut self.marked_moods, mood)
+            }
+            SelectedList::Decade => {
+                let decade = self.selected_decade();
+                toggle(&mut self.marked_decades, decade)
+            }
+            SelectedList::Country => {
+                if let Some(country) = self.selected_country() {
+                    toggle(&mut self.marked_countries, country)
+                }
+            }
+        }
+    }
+
+    /// Unmarks every entry of the focused list.
+    pub fn clear_marks(&mut self) {
+        match self.list_selected {
+            SelectedList::Mood => self.marked_moods.clear(),
+            SelectedList::Decade => self.marked_decades.clear(),
+            SelectedList::Country => self.marked_countries.clear(),
+        }
+    }
+
+    /// Starts filtering the focused list.
+    pub fn start_filter(&mut self) {
+        self.filter = Some(Filter::new(self.list_selected));
+        self.refresh_filter();
+    }
+
+    /// Types `c` in the query of the filter.
+    pub fn filter_push(&mut self, c: char) {
+        if let Some(filter) = &mut self.filter {
+            filter.query.push(c);
+            self.refresh_filter();
+        }
+    }
+
+    /// Erases the last character of the query, stops filtering once it is
+    /// empty.
+    pub fn filter_pop(&mut self) {
+        let erased = self
+            .filter
+            .as_mut()
+            .and_then(|filter| filter.query.pop())
+            .is_some();
+        if erased {
+            self.refresh_filter();
+        } else {
+            self.filter = None;
+        }
+    }
+
+    /// Moves the cursor of the list to the match under the cursor of the
+    /// filter, and stops filtering.
+    pub fn apply_filter(&mut self) {
+        let Some(filter) = self.filter.take() else {
+            return;
+        };
+        if let Some(index) = filter.selected() {
+            match filter.list {
+                SelectedList::Mood => self.mood_state.select(Some(index)),
+                SelectedList::Decade => self.decade_state.select(Some(index)),
+                SelectedList::Country => self.country_state.select(Some(index)),
+            }
+        }
+    }
+
+    fn refresh_filter(&mut self) {
+        let Some(list) = self.filter.as_ref().map(|filter| filter.list) else {
+            return;
+        };
+        let entries: Vec<Vec<String>> = match list {
+            SelectedList::Mood => Mood::iter().map(|m| vec![m.to_string()]).collect(),
+            SelectedList::Decade => Decade::iter().map(|d| vec![d.to_string()]).collect(),
+            SelectedList::Country => self
+                .get_countries_available()
+                .into_iter()
+                .map(|code| {
+                    let country = code.country();
+                    [country.alpha3, country.alpha2, country.name]
+                        .map(String::from)
+                        .to_vec()
+                })
+                .collect(),
+        };
+        if let Some(filter) = &mut self.filter {
+            filter.update(&entries);
+        }
+    }
+
+    /// Fills the availability of the countries, from the cache when there is
+    /// one. A stale cache is shown right away and refreshed in the background.
     pub fn populate_countries_available(&mut self) {
-        for &decade in &radiooo::DECADES {
-            if let Some(ca) = radiooo::get_country_for_decade(decade) {
-                self.country_availables.insert(decade, ca);
-            } else {
-                error!("failed to call for {}", decade);
-                panic!("qsdqsd")
+        let cached = match self
+            .availability_cache
+            .as_ref()
+            .map(AvailabilityCache::load)
+        {
+            Some(Ok(cached)) => cached,
+            Some(Err(err)) => {
+                error!("{}", err);
+                None
+            }
+            None => None,
+        };
+        match cached {
+            Some(cached) => {
+                self.country_availables = cached.availability;
+                if cached.stale {
+                    self.refresh_countries_available();
+                }
+            }
+            None => self.refresh_countries_available(),
+        }
+    }
+
+    /// Requests the availability again, see [`App::populate_countries_available`].
+    pub fn refresh_countries_available(&mut self) {
+        if let Some(handle) = self.availability_request.take() {
+            handle.abort();
+        }
+        let client = self.client.clone();
+        self.availability_request =
+            self.request(async move { Response::Availability(availability::fetch(&client).await) });
+    }
+
+    /// Replaces the availability and caches it, the cursor stays on the same
+    /// country if it is still available.
+    fn set_countries_available(&mut self, availability: Availability) {
+        let selected = self.selected_country();
+        self.country_availables = availability;
+        self.select(None, None, selected);
+        self.refresh_filter();
+        if let Some(cache) = &self.availability_cache {
+            if let Err(err) = cache.save(&self.country_availables) {
+                error!("{}", err);
             }
         }
     }
 
+    /// Keeps a failure around so the UI can display it.
+    pub fn report_error(&mut self, err: impl fmt::Display) {
+        self.error = Some(err.to_string());
+    }
+
+    /// Reports that the player could not play the current track, the caller
+    /// moves on to the next one.
+    pub fn track_failed(&mut self) {
+        let Some(track) = self.play_state.track() else {
+            return;
+        };
+        let notice = format!("failed to play {}, skipping it", track.title);
+        error!("{}", notice);
+        self.notify(notice);
+    }
+
+    /// Shows `notice` for [`NOTICE_TTL`].
+    pub fn notify(&mut self, notice: impl Into<String>) {
+        self.notice = Some((notice.into(), Instant::now()));
+    }
+
+    /// The notice, unless it has been shown for [`NOTICE_TTL`] already.
+    pub fn fresh_notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < NOTICE_TTL)
+            .map(|(notice, _)| notice.as_str())
+    }
+
+    /// Tunes the radio to `station` and starts playing from it.
+    pub fn tune(&mut self, station: ExploreRequest) {
+        if self.remote.is_some() {
+            return self.call_daemon("set_selection", json!(station));
+        }
+        self.station = Some(station);
+        self.next_track();
+    }
+
+    /// Fetches the next track of the current station and plays it.
+    pub fn next_track(&mut self) {
+        if self.remote.is_some() {
+            return self.call_daemon("next", Value::Null);
+        }
+        let Some(station) = self.station.clone() else {
+            return;
+        };
+        let client = self.client.clone();
+        self.request_track(TrackRequest::Next, async move {
+            client.get_track(&station).await
+        });
+    }
+
+    /// Starts playing `track` in place of the current one and records it in
+    /// the history.
+    pub fn play(&mut self, track: Track) {
+        if self.remote.is_some() {
+            return self.call_daemon("play_track", json!({ "track": track }));
+        }
+        if !self.start(track.clone()) {
+            return;
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(track);
+        self.history_cursor = Some(self.history.len() - 1);
+    }
+
+    /// Goes back to the track played before the current one in the history.
+    ///
+    /// Stream links expire, so they are requested again when needed.
+    pub fn previous_track(&mut self) {
+        if self.remote.is_some() {
+            return self.call_daemon("previous", Value::Null);
+        }
+        let Some(index) = self.history_cursor.and_then(|i| i.checked_sub(1)) else {
+            return;
+        };
+        self.refresh_links(TrackRequest::Previous(index), self.history[index].clone());
+    }
+
+    /// Plays `track` with fresh stream links, e.g. a favorite.
+    pub fn play_track(&mut self, track: Track) {
+        self.refresh_links(TrackRequest::Play, track);
+    }
+
+    /// Stops playback, the station is kept.
+    pub fn stop(&mut self) {
+        match self.player.stop() {
+            Ok(()) => {
+                self.play_state = PlayState::Stopped;
+                self.poll_progress();
+            }
+            Err(err) => self.report_error(err),
+        }
+    }
+
+    /// Sends `method` to the daemon in the background, the state it returns
+    /// is mirrored once it comes back as a [`Response::Daemon`].
+    fn call_daemon(&mut self, method: &str, params: Value) {
+        let Some(remote) = self.remote.clone() else {
+            return;
+        };
+        let method = method.to_string();
+        self.syncing = true;
+        self.request(async move {
+            let result = task::spawn_blocking(move || remote.execute(&method, params))
+                .await
+                .unwrap_or_else(|err| Err(io::Error::other(err).into()));
+            Response::Daemon(result.map(Box::new))
+        });
+    }
+
+    /// Mirrors the state of the daemon.
+    fn sync(&mut self, status: Status) {
+        let play_state = status.play_state();
+        if let Some(track) = play_state.track() {
+            if self.play_state.track().map(|t| &t._id) != Some(&track._id) {
+                self.error = None;
+                self.current_setting = setting(track);
+                self.load_cover(track);
+                if self.history.len() == HISTORY_LEN {
+                    self.history.pop_front();
+                }
+                self.history.push_back(track.clone());
+                self.history_cursor = Some(self.history.len() - 1);
+            }
+        }
+        self.play_state = play_state;
+        self.position = status.position.map(Duration::from_secs_f64);
+        self.duration = status.duration.map(Duration::from_secs_f64);
+        self.volume = status.volume;
+        self.muted = status.muted;
+        self.station = status.station;
+        if let Some(err) = status.error {
+            self.error = Some(err);
+        }
+    }
+
+    /// Requests new stream links for `track` if its own have expired.
+    fn refresh_links(&mut self, request: TrackRequest, track: Track) {
+        if !track.links.is_expired() {
+            self.cancel_track_request();
+            return self.on_track(request, Ok(track));
+        }
+        let client = self.client.clone();
+        self.request_track(
+            request,
+            async move { client.get_track_by_id(&track._id).await },
+        );
+    }
+
+    /// Runs `request` in the background, its response comes back as an
+    /// [`Event::Response`] to pass to [`App::handle_response`]. Without
+    /// [`App::events`] it is awaited and handled right away.
+    fn request(
+        &mut self,
+        request: impl Future<Output = Response> + Send + 'static,
+    ) -> Option<JoinHandle<()>> {
+        match &self.events {
+            Some(events) => {
+                let events = events.clone();
+                Some(tokio::spawn(async move {
+                    let _ = events.send(Event::Response(Box::new(request.await)));
+                }))
+            }
+            None => {
+                let response = block_on(request);
+                self.handle_response(response);
+                None
+            }
+        }
+    }
+
+    /// Requests a track, aborting the previous request still in flight.
+    fn request_track(
+        &mut self,
+        request: TrackRequest,
+        track: impl Future<Output = radiooo::Result<Track>> + Send + 'static,
+    ) {
+        self.cancel_track_request();
+        let id = self.track_request;
+        let handle = self.request(async move {
+            Response::Track {
+                id,
+                request,
+                result: track.await.map(Box::new),
+            }
+        });
+        self.tuning = handle.map(|handle| (handle, Instant::now()));
+    }
+
+    /// Aborts the track request in flight, a response already sent is
+    /// ignored as superseded.
+    fn cancel_track_request(&mut self) {
+        if let Some((handle, _)) = self.tuning.take() {
+            handle.abort();
+        }
+        self.track_request += 1;
+    }
+
+    /// Applies the outcome of a background request.
+    pub fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::Track {
+                id,
+                request,
+                result,
+            } => {
+                // superseded by a newer request
+                if id != self.track_request {
+                    return;
+                }
+                self.tuning = None;
+                self.on_track(request, result.map(|track| *track));
+            }
+            Response::Availability(result) => {
+                self.availability_request = None;
+                match result {
+                    Ok(availability) => self.set_countries_available(availability),
+                    Err(err) => {
+                        error!("failed to fetch the available countries: {}", err);
+                        // a stale availability is better than none
+                        if self.country_availables.is_empty() {
+                            self.report_error(err);
+                        }
+                    }
+                }
+            }
+            Response::Cover { uuid, result } => {
+                if self.play_state.track().map(|t| &t.uuid) != Some(&uuid) {
+                    return;
+                }
+                self.cover = match result {
+                    Ok(cover) => Some(cover),
+                    Err(CoverError::Missing) => None,
+                    Err(err) => {
+                        error!("failed to load the cover of {}: {}", uuid, err);
+                        None
+                    }
+                };
+            }
+            Response::Daemon(result) => {
+                self.syncing = false;
+                match result {
+                    Ok(status) => self.sync(*status),
+                    Err(err) => self.report_error(err),
+                }
+            }
+        }
+    }
+
+    /// Plays a requested track.
+    fn on_track(&mut self, request: TrackRequest, result: radiooo::Result<Track>) {
+        let track = match result {
+            Ok(track) => track,
+            Err(radiooo::RadioooError::NoTrackAvailable) if request == TrackRequest::Next => {
+                return self.report_error("no track was found for current setting");
+            }
+            Err(err) => {
+                error!("failed to get a track: {}", err);
+                return self.report_error(err);
+            }
+        };
+        info!("{:?}", track);
+        match request {
+            TrackRequest::Next | TrackRequest::Play => self.play(track),
+            TrackRequest::Previous(index) => {
+                self.history[index] = track.clone();
+                if self.start(track) {
+                    self.history_cursor = Some(index);
+                }
+            }
+        }
+    }
+
+    /// Whether the current track is in the library.
+    pub fn is_liked(&self) -> bool {
+        match (&self.library, self.play_state.track()) {
+            (Some(library), Some(track)) => library.contains(track),
+            _ => false,
+        }
+    }
+
+    /// Likes the current track, or unlikes it if it was already.
+    pub fn toggle_favorite(&mut self) {
+        let (Some(library), Some(track)) = (&mut self.library, self.play_state.track()) else {
+            return;
+        };
+        if let Err(err) = library.toggle(track) {
+            error!("failed to save the library: {}", err);
+            self.report_error(err);
+        }
+    }
+
+    /// Switches between the lists and the favorites.
+    pub fn toggle_favorites_view(&mut self) {
+        self.view = match self.view {
+            View::Browse => {
+                if self.favorites_state.selected().is_none() {
+                    self.favorites_state.select(Some(0));
+                }
+                View::Favorites
+            }
+            View::Favorites => View::Browse,
+        };
+    }
+
+    /// Plays the favorite under the cursor with fresh stream links.
+    pub fn play_favorite(&mut self) {
+        let favorite = self.library.as_ref().and_then(|library| {
+            library
+                .favorites
+                .get(self.favorites_state.selected().unwrap_or(0))
+        });
+        let Some(track) = favorite.map(|favorite| favorite.track.clone()) else {
+            return;
+        };
+        self.play_track(track);
+    }
+
+    /// Saves the current track in the background.
+    pub fn save_track(&mut self) {
+        if let Some(track) = self.play_state.track().cloned() {
+            self.download(track);
+        }
+    }
+
+    /// Saves the favorite under the cursor in the background.
+    pub fn save_favorite(&mut self) {
+        let favorite = self.library.as_ref().and_then(|library| {
+            library
+                .favorites
+                .get(self.favorites_state.selected().unwrap_or(0))
+        });
+        if let Some(track) = favorite.map(|favorite| favorite.track.clone()) {
+            self.download(track);
+        }
+    }
+
+    fn download(&mut self, track: Track) {
+        let Some(downloader) = self.downloader.clone() else {
+            self.notify("no music directory to save to");
+            return;
+        };
+        let title = format!("{} - {}", track.artist, track.title);
+        self.notify(format!("saving {}…", title));
+        let handle = tokio::spawn(async move { downloader.download(&track).await });
+        self.downloads.push((title, handle));
+    }
+
+    /// Reports the downloads that finished since the last tick.
+    fn poll_downloads(&mut self) {
+        let (finished, pending) = self
+            .downloads
+            .drain(..)
+            .partition(|(_, handle)| handle.is_finished());
+        self.downloads = pending;
+        for (title, handle) in finished {
+            match block_on(handle) {
+                Ok(Ok(path)) => {
+                    info!("saved {} to {}", title, path.display());
+                    self.notify(format!("saved {}", path.display()));
+                }
+                Ok(Err(err)) => {
+                    error!("failed to save {}: {}", title, err);
+                    self.notify(format!("failed to save {}", title));
+                    self.report_error(err);
+                }
+                Err(err) => {
+                    error!("download of {} panicked: {}", title, err);
+                    self.notify(format!("failed to save {}", title));
+                }
+            }
+        }
+    }
+
+    /// Loads `track` in the player and makes it the current one.
+    fn start(&mut self, track: Track) -> bool {
+        if let Err(err) = self.player.load(self.format.url(&track.links)) {
+            error!("failed to load the track: {}", err);
+            self.report_error(err);
+            return false;
+        }
+        self.error = None;
+        self.current_setting = setting(&track);
+        self.load_cover(&track);
+        self.listened_at = Some(scrobble::now());
+        self.played = Duration::ZERO;
+        self.last_progress = None;
+        self.announce(&track);
+        self.play_state = PlayState::Playing(track);
+        self.poll_progress();
+        true
+    }
+
+    /// Cover drawn over the frame by the [`crate::cover::Overlay`], none
+    /// while the help popup covers it.
+    pub fn visible_cover(&mut self) -> Option<&mut Cover> {
+        if self.show_help {
+            return None;
+        }
+        self.cover.as_mut()
+    }
+
+    /// Fetches the cover of `track`, a missing cover is not worth an error.
+    fn load_cover(&mut self, track: &Track) {
+        if self.cover.as_ref().is_some_and(|c| c.uuid == track.uuid) {
+            return;
+        }
+        self.cover = None;
+        let Some(covers) = self.covers.clone() else {
+            return;
+        };
+        let track = track.clone();
+        self.request(async move {
+            Response::Cover {
+                result: covers.get(&track).await,
+                uuid: track.uuid,
+            }
+        });
+    }
+
     pub fn playpause(&mut self) {
-        match &self.play_state {
+        let result = match &self.play_state {
             PlayState::Playing(track) => {
-                // send pause to mpv here
-                self.play_state = PlayState::Paused(track.clone());
+                let track = track.clone();
+                self.player
+                    .pause()
+                    .map(|_| self.play_state = PlayState::Paused(track))
             }
             PlayState::Paused(track) => {
-                // run play here and change state
-                self.play_state = PlayState::Playing(track.clone());
+                let track = track.clone();
+                self.player
+                    .resume()
+                    .map(|_| self.play_state = PlayState::Playing(track))
+            }
+            PlayState::Stopped => Ok(()),
+        };
+        if let Err(err) = result {
+            self.report_error(err);
+        }
+    }
+
+    /// Applies a command received from outside the terminal.
+    pub fn control(&mut self, control: Control) {
+        match control {
+            Control::Play => match self.play_state {
+                PlayState::Paused(_) => self.playpause(),
+                PlayState::Stopped => self.next_track(),
+                PlayState::Playing(_) => {}
+            },
+            Control::Pause => {
+                if let PlayState::Playing(_) = self.play_state {
+                    self.playpause();
+                }
             }
-            _ => {}
+            Control::PlayPause => match self.play_state {
+                PlayState::Stopped => self.next_track(),
+                _ => self.playpause(),
+            },
+            Control::Stop => self.stop(),
+            Control::Next => self.next_track(),
+            Control::Previous => self.previous_track(),
+            Control::SetVolume(volume) => self.set_volume(volume),
+            Control::Seek(offset) => self.seek(offset),
+            Control::SetPosition(position) => self.set_position(position),
+            Control::Quit => self.quit(),
         }
     }
+
     pub fn toggle_mute(&mut self) {
-        self.muted = !self.muted
+        match self.player.set_muted(!self.muted) {
+            Ok(()) => self.muted = !self.muted,
+            Err(err) => self.report_error(err),
+        }
     }
 
     pub fn increment_volume(&mut self) {
-        if let Some(res) = self.volume.checked_add(VOLUME_INCREMENT) {
-            if res < MAX_VOLUME {
-                self.volume = res;
-            }
-        }
+        self.set_volume(self.volume.saturating_add(self.volume_increment));
     }
 
     pub fn decrement_volume(&mut self) {
-        if let Some(res) = self.volume.checked_sub(VOLUME_INCREMENT) {
-            self.volume = res;
+        self.set_volume(self.volume.saturating_sub(self.volume_increment));
+    }
+
+    /// Sets the volume, in percent, up to [`App::max_volume`].
+    pub fn set_volume(&mut self, volume: u16) {
+        self.volume = volume.min(self.max_volume);
+        self.apply_volume();
+    }
+
+    /// Sends the current volume to the player.
+    fn apply_volume(&mut self) {
+        if let Err(err) = self.player.set_volume(self.volume) {
+            self.report_error(err);
+        }
+    }
+}
+
+/// `title - artist - album`, shown in the header.
+fn setting(track: &Track) -> String {
+    format!(
+        "{} - {} - {}",
+        track.title,
+        track.artist,
+        track.album.as_deref().unwrap_or_default()
+    )
+}
+
+/// Runs an API future to completion from the synchronous event loop.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    task::block_in_place(|| Handle::current().block_on(future))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::FakePlayer;
+    use crate::radiooo::tests::{sample_track, TRACK_JSON};
+    use tokio::sync::mpsc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_taxi_fetches_the_next_track() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(TRACK_JSON, "application/json"))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let mut player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client);
+
+        // nothing to advance to before the radio is tuned
+        app.next_track();
+        assert_eq!(app.play_state, PlayState::Stopped);
+
+        app.tune(ExploreRequest::new(
+            vec![Mood::Fast],
+            vec!["1970".parse().unwrap()],
+            vec!["ARG".parse().unwrap()],
+        ));
+        assert!(matches!(app.play_state, PlayState::Playing(_)));
+
+        player.stop().unwrap();
+        app.next_track();
+        assert!(player.state().loaded.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_track_request_in_background() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(TRACK_JSON, "application/json")
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .expect(1..=2)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.events = Some(sender);
+
+        app.tune(ExploreRequest::new(
+            vec![Mood::Fast],
+            vec!["1970".parse().unwrap()],
+            vec!["ARG".parse().unwrap()],
+        ));
+        assert!(app.tuning.is_some());
+        assert_eq!(app.play_state, PlayState::Stopped);
+
+        // the newer request supersedes the first one
+        app.next_track();
+        while app.tuning.is_some() {
+            let Some(Event::Response(response)) = events.recv().await else {
+                panic!("expected a track");
+            };
+            app.handle_response(*response);
+        }
+        assert!(matches!(app.play_state, PlayState::Playing(_)));
+        assert_eq!(app.history.len(), 1);
+
+        // a favorite with valid links cancels the request in flight
+        app.next_track();
+        assert!(app.tuning.is_some());
+        let mut favorite = sample_track();
+        favorite.links.mpeg = String::from("https://example.com/favorite.mp3");
+        app.play_track(favorite.clone());
+        assert!(app.tuning.is_none());
+        assert_eq!(app.play_state, PlayState::Playing(favorite.clone()));
+        // the aborted request sends nothing, or a response now superseded
+        let late = tokio::time::timeout(Duration::from_millis(300), events.recv()).await;
+        if let Ok(Some(Event::Response(response))) = late {
+            app.handle_response(*response);
+        }
+        assert_eq!(app.play_state, PlayState::Playing(favorite));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_notices_expire() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.notify("saving Artist - Title…");
+        let failed = tokio::spawn(async { Err(DownloadError::Ogg(String::from("bad"))) });
+        app.downloads.push((String::from("Artist - Title"), failed));
+        while !app.downloads.is_empty() {
+            tokio::task::yield_now().await;
+            app.tick();
+        }
+        let (notice, at) = app.notice.clone().unwrap();
+        assert_eq!(notice, "failed to save Artist - Title");
+        assert!(app.error.is_some());
+
+        app.notice = Some((notice, at - NOTICE_TTL));
+        app.tick();
+        assert_eq!(app.notice, None);
+    }
+
+    #[test]
+    fn test_multi_selection() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        let country = |code: &str| code.parse::<CountryCode>().unwrap();
+        let ca = |fast: &[&str], weird: &[&str]| radiooo::CountryForDecade {
+            fast: fast.iter().map(|c| country(c)).collect(),
+            weird: weird.iter().map(|c| country(c)).collect(),
+            ..Default::default()
+        };
+        app.country_availables
+            .insert(Decade::ALL[6], ca(&["FRA", "ITA"], &["ESP"]));
+        app.country_availables
+            .insert(Decade::ALL[7], ca(&["ARG"], &["FRA"]));
+
+        // FAST + WEIRD across 1960-1970
+        app.list_selected = SelectedList::Mood;
+        app.mood_state.select(Some(1));
+        app.toggle_mark();
+        app.mood_state.select(Some(2));
+        app.toggle_mark();
+        app.list_selected = SelectedList::Decade;
+        app.decade_state.select(Some(6));
+        app.toggle_mark();
+        app.decade_state.select(Some(7));
+        app.toggle_mark();
+        // sorted by name, Spain comes last
+        assert_eq!(
+            app.get_countries_available(),
+            ["ARG", "FRA", "ITA", "ESP"].map(country)
+        );
+
+        // without marked countries, only the one under the cursor is used
+        app.list_selected = SelectedList::Country;
+        app.country_state.select(Some(1));
+        assert_eq!(app.selection().unwrap().isocodes, [country("FRA")]);
+
+        app.toggle_mark();
+        app.country_state.select(Some(2));
+        app.toggle_mark();
+        app.country_state.select(Some(3));
+        app.toggle_mark();
+        let selection = app.selection().unwrap();
+        assert_eq!(selection.moods, [Mood::Weird, Mood::Fast]);
+        assert_eq!(selection.decades, [Decade::ALL[6], Decade::ALL[7]]);
+        assert_eq!(selection.isocodes, ["FRA", "ITA", "ESP"].map(country));
+
+        app.clear_marks();
+        assert_eq!(app.selection().unwrap().isocodes, [country("ESP")]);
+    }
+
+    #[test]
+    fn test_cycle_mode() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        assert_eq!(app.mode, PlayMode::Taxi);
+        app.cycle_mode();
+        assert_eq!(app.mode, PlayMode::Explore);
+        app.cycle_mode();
+        app.cycle_mode();
+        assert_eq!(app.mode, PlayMode::Taxi);
+
+        app.island = Some(String::from("5d330a5a"));
+        app.cycle_mode();
+        assert_eq!(app.mode, PlayMode::Islands);
+        app.cycle_mode();
+        assert_eq!(app.mode, PlayMode::Explore);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_favorites() {
+        let track = sample_track();
+        let mut fresh = sample_track();
+        fresh.links.mpeg = format!("{}&fresh&expires={}", track.links.mpeg, u32::MAX);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/track/play/{}", track._id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&fresh))
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("favorites.json");
+        app.library = Some(Library::open(&path).unwrap());
+
+        app.play(track.clone());
+        assert!(!app.is_liked());
+        app.toggle_favorite();
+        assert!(app.is_liked());
+        assert!(Library::open(&path).unwrap().contains(&track));
+
+        app.toggle_favorites_view();
+        assert_eq!(app.view, View::Favorites);
+        app.play_favorite();
+        assert_eq!(player.state().loaded, Some(fresh.links.mpeg.clone()));
+        assert_eq!(app.history.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_history() {
+        let track = sample_track();
+        let mut fresh = sample_track();
+        fresh.links.mpeg = format!("{}/fresh.mp3?expires={}", track.links.mpeg, u32::MAX);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/track/play/{}", track._id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&fresh))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client);
+
+        // the sample track links expired long ago
+        app.play(track.clone());
+        let mut second = sample_track();
+        second._id = String::from("second");
+        app.play(second);
+        assert_eq!(app.history.len(), 2);
+        assert_eq!(app.history_cursor, Some(1));
+
+        app.previous_track();
+        assert_eq!(app.history_cursor, Some(0));
+        assert_eq!(app.history[0], fresh);
+        assert_eq!(player.state().loaded, Some(fresh.links.mpeg.clone()));
+
+        // already at the oldest track
+        app.previous_track();
+        assert_eq!(app.history_cursor, Some(0));
+
+        for _ in 0..HISTORY_LEN {
+            app.play(sample_track());
+        }
+        assert_eq!(app.history.len(), HISTORY_LEN);
+        assert_eq!(app.history_cursor, Some(HISTORY_LEN - 1));
+    }
+
+    /// Waits for `server` to receive `count` requests, returns their bodies.
+    async fn received(server: &MockServer, count: usize) -> Vec<Value> {
+        for _ in 0..100 {
+            let requests = server.received_requests().await.unwrap();
+            if requests.len() >= count {
+                return requests.iter().map(|r| r.body_json().unwrap()).collect();
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        panic!("{} requests were not received", count);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_scrobble_past_threshold() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/1/submit-listens"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder().build().unwrap();
+        let player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client);
+        app.scrobbler = Some(Scrobbler::new(&server.uri(), "secret").unwrap());
+
+        app.play(sample_track());
+        assert_eq!(received(&server, 1).await[0]["listen_type"], "playing_now");
+
+        // seeking past half of the 199s track is not playing it
+        app.seek(110.0);
+        app.tick();
+        assert!(app.listened_at.is_some());
+
+        // 90s later, 10s short of the threshold
+        let played = |app: &mut App, secs: u64| {
+            let (position, at) = app.last_progress.unwrap();
+            let elapsed = Duration::from_secs(secs);
+            app.last_progress = Some((position, at.checked_sub(elapsed).unwrap()));
+            Player::seek(&mut player.clone(), secs as f64).unwrap();
+            app.tick();
+        };
+        played(&mut app, 90);
+        assert!(app.listened_at.is_some());
+        assert_eq!(app.played.as_secs(), 90);
+
+        played(&mut app, 10);
+        assert!(app.listened_at.is_none());
+        assert_eq!(received(&server, 2).await[1]["listen_type"], "single");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_stale_availability_is_refreshed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"SLOW": ["FRA"], "FAST": [], "WEIRD": []}"#),
+            )
+            .expect(13)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AvailabilityCache::new(dir.path().join("availability.json"));
+        let argentina = radiooo::CountryForDecade {
+            slow: vec![CountryCode::new("ARG").unwrap()],
+            ..Default::default()
+        };
+        cache
+            .save(&Decade::iter().map(|d| (d, argentina.clone())).collect())
+            .unwrap();
+
+        // the stale cache is shown while the API is asked again
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.events = Some(sender);
+        app.availability_cache = Some(cache.clone().ttl(Duration::ZERO));
+        app.populate_countries_available();
+        assert_eq!(app.get_countries_available(), argentina.slow);
+        assert!(app.availability_request.is_some());
+
+        let Some(Event::Response(response)) = events.recv().await else {
+            panic!("expected the availability");
+        };
+        app.handle_response(*response);
+        assert!(app.availability_request.is_none());
+        let france = [CountryCode::new("FRA").unwrap()];
+        assert_eq!(app.get_countries_available(), france);
+        let cached = cache.load().unwrap().unwrap();
+        assert!(!cached.stale);
+        assert_eq!(cached.availability[&Decade::ALL[0]].slow, france);
     }
 }
@@ -0,0 +1,173 @@
+use crate::radiooo::{self, CountryForDecade, Decade, RadioooClient};
+use futures::future;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Countries having tracks for each decade.
+pub type Availability = HashMap<Decade, CountryForDecade>;
+
+/// Age after which the cached availability is refreshed.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Error returned while reading or writing the cache file.
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("failed to access the availability cache {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("the availability cache {path} is corrupted: {source}")]
+    Corrupted {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Requests the availability of every decade at once.
+pub async fn fetch(client: &RadioooClient) -> radiooo::Result<Availability> {
+    let requests = Decade::iter().map(|decade| async move {
+        let countries = client.get_country_for_decade(decade).await?;
+        Ok::<_, radiooo::RadioooError>((decade, countries))
+    });
+    Ok(future::try_join_all(requests).await?.into_iter().collect())
+}
+
+/// Availability read from the cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached {
+    pub availability: Availability,
+    /// Whether it is older than the time to live and should be refreshed.
+    pub stale: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    /// Seconds since the epoch.
+    fetched_at: u64,
+    decades: Availability,
+}
+
+/// Availability persisted as JSON, so that the lists show up without
+/// waiting for the API.
+#[derive(Debug, Clone)]
+pub struct AvailabilityCache {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+impl AvailabilityCache {
+    /// Constructs a new instance of [`AvailabilityCache`] stored at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Sets the age after which the cache is stale.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/radiooooo/availability.json`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("radiooooo").join("availability.json"))
+    }
+
+    /// Reads the cache, `None` if nothing was saved yet.
+    pub fn load(&self) -> Result<Option<Cached>, CacheError> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(CacheError::Io {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+        };
+        let file: CacheFile =
+            serde_json::from_slice(&bytes).map_err(|source| CacheError::Corrupted {
+                path: self.path.clone(),
+                source,
+            })?;
+        let age = SystemTime::now()
+            .duration_since(UNIX_EPOCH + Duration::from_secs(file.fetched_at))
+            .unwrap_or_default();
+        Ok(Some(Cached {
+            availability: file.decades,
+            stale: age >= self.ttl,
+        }))
+    }
+
+    /// Replaces the cache with `availability`, fetched just now.
+    pub fn save(&self, availability: &Availability) -> Result<(), CacheError> {
+        let io_error = |source| CacheError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let file = CacheFile {
+            fetched_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            decades: availability.clone(),
+        };
+        let json = serde_json::to_vec(&file).expect("the availability is serializable");
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(io_error)?;
+        fs::rename(&tmp, &self.path).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiooo::{CountryCode, Mood};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fetch_and_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(r#"{"SLOW": ["ARG"], "FAST": ["FRA", "ITA"], "WEIRD": []}"#),
+            )
+            .expect(13)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let availability = fetch(&client).await.unwrap();
+        assert_eq!(availability.len(), Decade::ALL.len());
+        assert_eq!(
+            availability[&Decade::ALL[7]].get(Mood::Slow),
+            [CountryCode::new("ARG").unwrap()]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AvailabilityCache::new(dir.path().join("availability.json"));
+        assert_eq!(cache.load().unwrap(), None);
+        cache.save(&availability).unwrap();
+        let cached = cache.load().unwrap().unwrap();
+        assert_eq!(cached.availability, availability);
+        assert!(!cached.stale);
+        assert!(cache.ttl(Duration::ZERO).load().unwrap().unwrap().stale);
+    }
+}
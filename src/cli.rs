@@ -0,0 +1,104 @@
+use crate::download::AudioFormat;
+use crate::radiooo::{CountryCode, Decade, Mood, PlayMode};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Listen to radiooooo from the terminal.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Play mode: explore, shuffle, taxi or islands [default: taxi].
+    #[arg(long, global = true)]
+    pub mode: Option<PlayMode>,
+
+    /// Island to play from, required by the islands mode.
+    #[arg(long, global = true, required_if_eq("mode", "islands"))]
+    pub island: Option<String>,
+
+    /// Directory the tracks are saved to, `$XDG_MUSIC_DIR/radiooooo` by default.
+    #[arg(long, global = true)]
+    pub music_dir: Option<PathBuf>,
+
+    /// Stream played and saved: mpeg or ogg [default: mpeg].
+    #[arg(long, global = true)]
+    pub format: Option<AudioFormat>,
+
+    /// Configuration file, `$XDG_CONFIG_HOME/radiooooo/config.toml` by default.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Socket of the daemon, `$XDG_RUNTIME_DIR/radiooooo.sock` by default.
+    #[arg(long, global = true)]
+    pub socket: Option<PathBuf>,
+
+    /// Control the daemon from the interface instead of playing in it.
+    #[arg(long)]
+    pub remote: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Moods, decades and countries to draw tracks from.
+#[derive(Debug, Args)]
+pub struct Selection {
+    /// Moods to draw from, e.g. `FAST,SLOW` [default: the configured one, or all].
+    #[arg(long = "mood", value_delimiter = ',')]
+    pub moods: Vec<Mood>,
+
+    /// Decades to draw from, e.g. `1960,1970` [default: the configured one].
+    #[arg(long = "decade", value_delimiter = ',')]
+    pub decades: Vec<Decade>,
+
+    /// Countries to draw from, e.g. `ARG,FRA` [default: the configured one].
+    #[arg(long = "country", value_delimiter = ',')]
+    pub countries: Vec<CountryCode>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Play without the interface, printing a line per track to stdout.
+    Play(Selection),
+
+    /// Play in the background, controlled through a socket by `ctl` and `--remote`.
+    Daemon,
+
+    /// Send a command to the daemon.
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+
+    /// Save tracks for offline listening, without starting the interface.
+    Download {
+        /// Ids of the tracks to save.
+        #[arg(required_unless_present = "favorites")]
+        ids: Vec<String>,
+
+        /// Save every favorite.
+        #[arg(long)]
+        favorites: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CtlCommand {
+    /// Resume, or start the station.
+    Play,
+    Pause,
+    Stop,
+    Next,
+    Previous,
+    /// Set the volume, in percent.
+    Volume {
+        volume: u16,
+    },
+    Mute,
+    Unmute,
+    /// Tune to a new selection.
+    Tune(Selection),
+    /// Print what is playing.
+    Status,
+    /// Stop the daemon.
+    Quit,
+}
@@ -0,0 +1,355 @@
+use crate::availability;
+use crate::cover::GraphicsProtocol;
+use crate::download::AudioFormat;
+use crate::keymap::Keymap;
+use crate::radiooo::{
+    CountryCode, Decade, Mood, PlayMode, DEFAULT_BASE_URL, DEFAULT_COVER_BASE_URL,
+};
+use crate::scrobble;
+use reqwest::Url;
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Error returned while loading the configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read the configuration {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid configuration {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("invalid configuration {path}: {message}")]
+    Invalid { path: PathBuf, message: String },
+}
+
+/// Settings read from `config.toml`, every key is optional.
+///
+/// ```toml
+/// log_file = "log/requests.log"
+///
+/// [player]
+/// volume = 50
+/// max_volume = 150
+/// volume_increment = 5
+/// format = "mpeg" # or "ogg"
+///
+/// [api]
+/// base_url = "https://radiooooo.com"
+/// cover_base_url = "https://asset.radiooooo.com"
+/// availability_ttl = 86400 # seconds the countries of each decade are cached
+///
+/// [download]
+/// music_dir = "~/Music/radiooooo"
+///
+/// [defaults]
+/// mode = "taxi"
+/// mood = "FAST"
+/// decade = 1970
+/// country = "ARG"
+///
+/// [scrobble] # disabled without a token
+/// url = "https://api.listenbrainz.org"
+/// token = "…"
+///
+/// [ui]
+/// tick_rate = 250 # milliseconds
+/// covers = true
+/// graphics = "kitty" # or "sixel", "half-blocks", detected when unset
+///
+/// [keys] # see `Keymap`
+/// play_pause = "p"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log_file: PathBuf,
+    pub player: PlayerConfig,
+    pub api: ApiConfig,
+    pub download: DownloadConfig,
+    pub defaults: DefaultsConfig,
+    pub scrobble: ScrobbleConfig,
+    pub ui: UiConfig,
+    pub keys: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_file: PathBuf::from("log/requests.log"),
+            player: PlayerConfig::default(),
+            api: ApiConfig::default(),
+            download: DownloadConfig::default(),
+            defaults: DefaultsConfig::default(),
+            scrobble: ScrobbleConfig::default(),
+            ui: UiConfig::default(),
+            keys: Keymap::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    /// Volume at startup, in percent.
+    pub volume: u16,
+    pub max_volume: u16,
+    /// Step of the volume keys.
+    pub volume_increment: u16,
+    /// Stream played and saved.
+    pub format: AudioFormat,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            volume: 50,
+            max_volume: 150,
+            volume_increment: 5,
+            format: AudioFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub base_url: String,
+    pub cover_base_url: String,
+    /// Seconds after which the cached availability is refreshed.
+    pub availability_ttl: u64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            cover_base_url: DEFAULT_COVER_BASE_URL.to_string(),
+            availability_ttl: availability::DEFAULT_TTL.as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownloadConfig {
+    /// `$XDG_MUSIC_DIR/radiooooo` when unset, a leading `~/` is expanded.
+    pub music_dir: Option<PathBuf>,
+}
+
+/// Selection of the lists at startup.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DefaultsConfig {
+    pub mode: PlayMode,
+    pub mood: Option<Mood>,
+    pub decade: Option<Decade>,
+    pub country: Option<CountryCode>,
+}
+
+/// ListenBrainz compatible server the played tracks are submitted to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScrobbleConfig {
+    pub url: String,
+    /// User token, nothing is submitted without it.
+    pub token: Option<String>,
+}
+
+impl Default for ScrobbleConfig {
+    fn default() -> Self {
+        Self {
+            url: scrobble::DEFAULT_URL.to_string(),
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    /// Interval between two redraws, in milliseconds.
+    pub tick_rate: u64,
+    /// Whether to show the cover of the current track.
+    pub covers: bool,
+    /// Detected from the terminal when unset.
+    pub graphics: Option<GraphicsProtocol>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: 250,
+            covers: true,
+            graphics: None,
+        }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/radiooooo/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("radiooooo").join("config.toml"))
+    }
+
+    /// Reads and validates the configuration at `path`, the defaults are used
+    /// if it does not exist.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(source) => {
+                return Err(ConfigError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+        let config: Self = toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.validate().map_err(|message| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        Ok(config)
+    }
+
+    /// Checks the values the types alone do not constrain.
+    pub fn validate(&self) -> Result<(), String> {
+        let player = &self.player;
+        if player.max_volume == 0 {
+            return Err(String::from("player.max_volume must be positive"));
+        }
+        if player.volume > player.max_volume {
+            return Err(format!(
+                "player.volume ({}) exceeds player.max_volume ({})",
+                player.volume, player.max_volume
+            ));
+        }
+        if player.volume_increment == 0 || player.volume_increment > player.max_volume {
+            return Err(format!(
+                "player.volume_increment must be between 1 and {}",
+                player.max_volume
+            ));
+        }
+        for (key, url) in [
+            ("api.base_url", &self.api.base_url),
+            ("api.cover_base_url", &self.api.cover_base_url),
+            ("scrobble.url", &self.scrobble.url),
+        ] {
+            match Url::parse(url) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                Ok(_) => return Err(format!("{key} must be an http or https URL")),
+                Err(err) => return Err(format!("{key} is not a valid URL: {err}")),
+            }
+        }
+        if self.defaults.mode == PlayMode::Islands {
+            return Err(String::from(
+                "defaults.mode cannot be islands, the island is picked from the command line",
+            ));
+        }
+        if self
+            .scrobble
+            .token
+            .as_ref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return Err(String::from("scrobble.token cannot be empty"));
+        }
+        if self.ui.tick_rate == 0 {
+            return Err(String::from("ui.tick_rate must be positive"));
+        }
+        if self.log_file.as_os_str().is_empty() {
+            return Err(String::from("log_file cannot be empty"));
+        }
+        Ok(())
+    }
+
+    /// [`DownloadConfig::music_dir`] with `~` expanded.
+    pub fn music_dir(&self) -> Option<PathBuf> {
+        let dir = self.download.music_dir.as_ref()?;
+        match dir.strip_prefix("~") {
+            Ok(rest) => dirs::home_dir().map(|home| home.join(rest)),
+            Err(_) => Some(dir.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(content: &str) -> Result<Config, ConfigError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, content).unwrap();
+        Config::load(&path)
+    }
+
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(missing, Config::default());
+
+        let config = load(
+            r#"
+            [player]
+            volume = 80
+            format = "ogg"
+
+            [defaults]
+            mood = "SLOW"
+            decade = 1960
+            country = "FRA"
+
+            [scrobble]
+            token = "secret"
+
+            [ui]
+            graphics = "half-blocks"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.player.volume, 80);
+        assert_eq!(config.player.max_volume, 150);
+        assert_eq!(config.player.format, AudioFormat::Ogg);
+        assert_eq!(config.defaults.mood, Some(Mood::Slow));
+        assert_eq!(config.defaults.decade, Some(Decade::new(1960).unwrap()));
+        assert_eq!(
+            config.defaults.country,
+            Some(CountryCode::new("FRA").unwrap())
+        );
+        assert_eq!(config.scrobble.url, scrobble::DEFAULT_URL);
+        assert_eq!(config.scrobble.token.as_deref(), Some("secret"));
+        assert_eq!(config.ui.graphics, Some(GraphicsProtocol::HalfBlocks));
+        assert_eq!(config.ui.tick_rate, 250);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let error = |content| load(content).unwrap_err().to_string();
+
+        assert!(error("[player]\nvolume = 200").contains("player.volume (200) exceeds"));
+        assert!(error("[player]\nvolume_increment = 0").contains("player.volume_increment"));
+        assert!(error("[api]\nbase_url = \"ftp://example.com\"").contains("http or https"));
+        assert!(error("[ui]\ntick_rate = 0").contains("ui.tick_rate"));
+        assert!(error("[scrobble]\ntoken = \"\"").contains("scrobble.token"));
+        // the parser errors point at the offending line
+        assert!(error("[defaults]\ndecade = 1915").contains("line 2"));
+        assert!(error("[defaults]\ncountry = \"XXX\"").contains("XXX"));
+        assert!(error("[player]\nvolum = 50").contains("volum"));
+        assert!(error("[keys]\nquit = \"space\"").contains("bound to both"));
+    }
+}
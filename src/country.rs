@@ -0,0 +1,352 @@
+use std::fmt;
+
+/// Continent of a country, as in the UN M49 regions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Region {
+    Africa,
+    Americas,
+    Antarctica,
+    Asia,
+    Europe,
+    Oceania,
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Region::Africa => "Africa",
+            Region::Americas => "Americas",
+            Region::Antarctica => "Antarctica",
+            Region::Asia => "Asia",
+            Region::Europe => "Europe",
+            Region::Oceania => "Oceania",
+        })
+    }
+}
+
+/// A country or territory radiooooo has tracks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Country {
+    /// ISO 3166-1 alpha-3 code, the one used by the API.
+    pub alpha3: &'static str,
+    /// ISO 3166-1 alpha-2 code.
+    pub alpha2: &'static str,
+    /// Short English name.
+    pub name: &'static str,
+    pub region: Region,
+}
+
+impl Country {
+    /// Looks up an alpha-3 or alpha-2 code, ignoring case.
+    pub fn find(code: &str) -> Option<&'static Country> {
+        COUNTRIES.iter().find(|country| {
+            country.alpha3.eq_ignore_ascii_case(code) || country.alpha2.eq_ignore_ascii_case(code)
+        })
+    }
+
+    /// Flag emoji, made of the regional indicators of the alpha-2 code.
+    pub fn flag(&self) -> String {
+        self.alpha2
+            .chars()
+            .filter_map(|c| char::from_u32(0x1F1E6 + u32::from(c) - u32::from('A')))
+            .collect()
+    }
+}
+
+/// `🇦🇷 Argentina`.
+impl fmt::Display for Country {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.flag(), self.name)
+    }
+}
+
+const fn country(
+    alpha3: &'static str,
+    alpha2: &'static str,
+    name: &'static str,
+    region: Region,
+) -> Country {
+    Country {
+        alpha3,
+        alpha2,
+        name,
+        region,
+    }
+}
+
+/// Every country known to radiooooo.
+pub const COUNTRIES: [Country; 239] = [
+    country("AFG", "AF", "Afghanistan", Region::Asia),
+    country("ALB", "AL", "Albania", Region::Europe),
+    country("DZA", "DZ", "Algeria", Region::Africa),
+    country("ASM", "AS", "American Samoa", Region::Oceania),
+    country("AND", "AD", "Andorra", Region::Europe),
+    country("AGO", "AO", "Angola", Region::Africa),
+    country("AIA", "AI", "Anguilla", Region::Americas),
+    country("ATA", "AQ", "Antarctica", Region::Antarctica),
+    country("ATG", "AG", "Antigua and Barbuda", Region::Americas),
+    country("ARG", "AR", "Argentina", Region::Americas),
+    country("ARM", "AM", "Armenia", Region::Asia),
+    country("ABW", "AW", "Aruba", Region::Americas),
+    country("AUS", "AU", "Australia", Region::Oceania),
+    country("AUT", "AT", "Austria", Region::Europe),
+    country("AZE", "AZ", "Azerbaijan", Region::Asia),
+    country("BHS", "BS", "Bahamas", Region::Americas),
+    country("BHR", "BH", "Bahrain", Region::Asia),
+    country("BGD", "BD", "Bangladesh", Region::Asia),
+    country("BRB", "BB", "Barbados", Region::Americas),
+    country("BLR", "BY", "Belarus", Region::Europe),
+    country("BEL", "BE", "Belgium", Region::Europe),
+    country("BLZ", "BZ", "Belize", Region::Americas),
+    country("BEN", "BJ", "Benin", Region::Africa),
+    country("BMU", "BM", "Bermuda", Region::Americas),
+    country("BTN", "BT", "Bhutan", Region::Asia),
+    country("BOL", "BO", "Bolivia", Region::Americas),
+    country("BIH", "BA", "Bosnia and Herzegovina", Region::Europe),
+    country("BWA", "BW", "Botswana", Region::Africa),
+    country("BRA", "BR", "Brazil", Region::Americas),
+    country(
+        "IOT",
+        "IO",
+        "British Indian Ocean Territory",
+        Region::Africa,
+    ),
+    country("BRN", "BN", "Brunei", Region::Asia),
+    country("BGR", "BG", "Bulgaria", Region::Europe),
+    country("BFA", "BF", "Burkina Faso", Region::Africa),
+    country("BDI", "BI", "Burundi", Region::Africa),
+    country("CPV", "CV", "Cabo Verde", Region::Africa),
+    country("KHM", "KH", "Cambodia", Region::Asia),
+    country("CMR", "CM", "Cameroon", Region::Africa),
+    country("CAN", "CA", "Canada", Region::Americas),
+    country("CYM", "KY", "Cayman Islands", Region::Americas),
+    country("CAF", "CF", "Central African Republic", Region::Africa),
+    country("TCD", "TD", "Chad", Region::Africa),
+    country("CHL", "CL", "Chile", Region::Americas),
+    country("CHN", "CN", "China", Region::Asia),
+    country("CXR", "CX", "Christmas Island", Region::Oceania),
+    country("CCK", "CC", "Cocos Islands", Region::Oceania),
+    country("COL", "CO", "Colombia", Region::Americas),
+    country("COM", "KM", "Comoros", Region::Africa),
+    country("COG", "CG", "Congo", Region::Africa),
+    country("COD", "CD", "DR Congo", Region::Africa),
+    country("COK", "CK", "Cook Islands", Region::Oceania),
+    country("CRI", "CR", "Costa Rica", Region::Americas),
+    country("HRV", "HR", "Croatia", Region::Europe),
+    country("CUB", "CU", "Cuba", Region::Americas),
+    country("CUW", "CW", "Curaçao", Region::Americas),
+    country("CYP", "CY", "Cyprus", Region::Asia),
+    country("CZE", "CZ", "Czechia", Region::Europe),
+    country("DNK", "DK", "Denmark", Region::Europe),
+    country("DJI", "DJ", "Djibouti", Region::Africa),
+    country("DMA", "DM", "Dominica", Region::Americas),
+    country("DOM", "DO", "Dominican Republic", Region::Americas),
+    country("ECU", "EC", "Ecuador", Region::Americas),
+    country("EGY", "EG", "Egypt", Region::Africa),
+    country("SLV", "SV", "El Salvador", Region::Americas),
+    country("GNQ", "GQ", "Equatorial Guinea", Region::Africa),
+    country("ERI", "ER", "Eritrea", Region::Africa),
+    country("EST", "EE", "Estonia", Region::Europe),
+    country("SWZ", "SZ", "Eswatini", Region::Africa),
+    country("ETH", "ET", "Ethiopia", Region::Africa),
+    country("FJI", "FJ", "Fiji", Region::Oceania),
+    country("FIN", "FI", "Finland", Region::Europe),
+    country("FRA", "FR", "France", Region::Europe),
+    country("GUF", "GF", "French Guiana", Region::Americas),
+    country("PYF", "PF", "French Polynesia", Region::Oceania),
+    country("GAB", "GA", "Gabon", Region::Africa),
+    country("GMB", "GM", "Gambia", Region::Africa),
+    country("GEO", "GE", "Georgia", Region::Asia),
+    country("DEU", "DE", "Germany", Region::Europe),
+    country("GHA", "GH", "Ghana", Region::Africa),
+    country("GIB", "GI", "Gibraltar", Region::Europe),
+    country("GRC", "GR", "Greece", Region::Europe),
+    country("GRL", "GL", "Greenland", Region::Americas),
+    country("GRD", "GD", "Grenada", Region::Americas),
+    country("GLP", "GP", "Guadeloupe", Region::Americas),
+    country("GUM", "GU", "Guam", Region::Oceania),
+    country("GTM", "GT", "Guatemala", Region::Americas),
+    country("GGY", "GG", "Guernsey", Region::Europe),
+    country("GIN", "GN", "Guinea", Region::Africa),
+    country("GNB", "GW", "Guinea-Bissau", Region::Africa),
+    country("GUY", "GY", "Guyana", Region::Americas),
+    country("HTI", "HT", "Haiti", Region::Americas),
+    country("HND", "HN", "Honduras", Region::Americas),
+    country("HKG", "HK", "Hong Kong", Region::Asia),
+    country("HUN", "HU", "Hungary", Region::Europe),
+    country("ISL", "IS", "Iceland", Region::Europe),
+    country("IND", "IN", "India", Region::Asia),
+    country("IDN", "ID", "Indonesia", Region::Asia),
+    country("IRN", "IR", "Iran", Region::Asia),
+    country("IRQ", "IQ", "Iraq", Region::Asia),
+    country("IRL", "IE", "Ireland", Region::Europe),
+    country("IMN", "IM", "Isle of Man", Region::Europe),
+    country("ISR", "IL", "Israel", Region::Asia),
+    country("ITA", "IT", "Italy", Region::Europe),
+    country("CIV", "CI", "Côte d'Ivoire", Region::Africa),
+    country("JAM", "JM", "Jamaica", Region::Americas),
+    country("JPN", "JP", "Japan", Region::Asia),
+    country("JEY", "JE", "Jersey", Region::Europe),
+    country("JOR", "JO", "Jordan", Region::Asia),
+    country("KAZ", "KZ", "Kazakhstan", Region::Asia),
+    country("KEN", "KE", "Kenya", Region::Africa),
+    country("KIR", "KI", "Kiribati", Region::Oceania),
+    country("PRK", "KP", "North Korea", Region::Asia),
+    country("KOR", "KR", "South Korea", Region::Asia),
+    country("KWT", "KW", "Kuwait", Region::Asia),
+    country("KGZ", "KG", "Kyrgyzstan", Region::Asia),
+    country("LAO", "LA", "Laos", Region::Asia),
+    country("LVA", "LV", "Latvia", Region::Europe),
+    country("LBN", "LB", "Lebanon", Region::Asia),
+    country("LSO", "LS", "Lesotho", Region::Africa),
+    country("LBR", "LR", "Liberia", Region::Africa),
+    country("LBY", "LY", "Libya", Region::Africa),
+    country("LIE", "LI", "Liechtenstein", Region::Europe),
+    country("LTU", "LT", "Lithuania", Region::Europe),
+    country("LUX", "LU", "Luxembourg", Region::Europe),
+    country("MAC", "MO", "Macao", Region::Asia),
+    country("MDG", "MG", "Madagascar", Region::Africa),
+    country("MWI", "MW", "Malawi", Region::Africa),
+    country("MYS", "MY", "Malaysia", Region::Asia),
+    country("MDV", "MV", "Maldives", Region::Asia),
+    country("MLI", "ML", "Mali", Region::Africa),
+    country("MLT", "MT", "Malta", Region::Europe),
+    country("MHL", "MH", "Marshall Islands", Region::Oceania),
+    country("MTQ", "MQ", "Martinique", Region::Americas),
+    country("MRT", "MR", "Mauritania", Region::Africa),
+    country("MUS", "MU", "Mauritius", Region::Africa),
+    country("MYT", "YT", "Mayotte", Region::Africa),
+    country("MEX", "MX", "Mexico", Region::Americas),
+    country("FSM", "FM", "Micronesia", Region::Oceania),
+    country("MDA", "MD", "Moldova", Region::Europe),
+    country("MCO", "MC", "Monaco", Region::Europe),
+    country("MNG", "MN", "Mongolia", Region::Asia),
+    country("MNE", "ME", "Montenegro", Region::Europe),
+    country("MSR", "MS", "Montserrat", Region::Americas),
+    country("MAR", "MA", "Morocco", Region::Africa),
+    country("MOZ", "MZ", "Mozambique", Region::Africa),
+    country("MMR", "MM", "Myanmar", Region::Asia),
+    country("NAM", "NA", "Namibia", Region::Africa),
+    country("NRU", "NR", "Nauru", Region::Oceania),
+    country("NPL", "NP", "Nepal", Region::Asia),
+    country("NLD", "NL", "Netherlands", Region::Europe),
+    country("NCL", "NC", "New Caledonia", Region::Oceania),
+    country("NZL", "NZ", "New Zealand", Region::Oceania),
+    country("NIC", "NI", "Nicaragua", Region::Americas),
+    country("NER", "NE", "Niger", Region::Africa),
+    country("NGA", "NG", "Nigeria", Region::Africa),
+    country("NIU", "NU", "Niue", Region::Oceania),
+    country("NFK", "NF", "Norfolk Island", Region::Oceania),
+    country("MKD", "MK", "North Macedonia", Region::Europe),
+    country("MNP", "MP", "Northern Mariana Islands", Region::Oceania),
+    country("NOR", "NO", "Norway", Region::Europe),
+    country("OMN", "OM", "Oman", Region::Asia),
+    country("PAK", "PK", "Pakistan", Region::Asia),
+    country("PLW", "PW", "Palau", Region::Oceania),
+    country("PSE", "PS", "Palestine", Region::Asia),
+    country("PAN", "PA", "Panama", Region::Americas),
+    country("PNG", "PG", "Papua New Guinea", Region::Oceania),
+    country("PRY", "PY", "Paraguay", Region::Americas),
+    country("PER", "PE", "Peru", Region::Americas),
+    country("PHL", "PH", "Philippines", Region::Asia),
+    country("PCN", "PN", "Pitcairn Islands", Region::Oceania),
+    country("POL", "PL", "Poland", Region::Europe),
+    country("PRT", "PT", "Portugal", Region::Europe),
+    country("PRI", "PR", "Puerto Rico", Region::Americas),
+    country("QAT", "QA", "Qatar", Region::Asia),
+    country("ROU", "RO", "Romania", Region::Europe),
+    country("RUS", "RU", "Russia", Region::Europe),
+    country("RWA", "RW", "Rwanda", Region::Africa),
+    country("REU", "RE", "Réunion", Region::Africa),
+    country("BLM", "BL", "Saint Barthélemy", Region::Americas),
+    country("SHN", "SH", "Saint Helena", Region::Africa),
+    country("KNA", "KN", "Saint Kitts and Nevis", Region::Americas),
+    country("LCA", "LC", "Saint Lucia", Region::Americas),
+    country("MAF", "MF", "Saint Martin", Region::Americas),
+    country("SPM", "PM", "Saint Pierre and Miquelon", Region::Americas),
+    country(
+        "VCT",
+        "VC",
+        "Saint Vincent and the Grenadines",
+        Region::Americas,
+    ),
+    country("WSM", "WS", "Samoa", Region::Oceania),
+    country("SMR", "SM", "San Marino", Region::Europe),
+    country("STP", "ST", "Sao Tome and Principe", Region::Africa),
+    country("SAU", "SA", "Saudi Arabia", Region::Asia),
+    country("SEN", "SN", "Senegal", Region::Africa),
+    country("SRB", "RS", "Serbia", Region::Europe),
+    country("SYC", "SC", "Seychelles", Region::Africa),
+    country("SLE", "SL", "Sierra Leone", Region::Africa),
+    country("SGP", "SG", "Singapore", Region::Asia),
+    country("SXM", "SX", "Sint Maarten", Region::Americas),
+    country("SVK", "SK", "Slovakia", Region::Europe),
+    country("SVN", "SI", "Slovenia", Region::Europe),
+    country("SLB", "SB", "Solomon Islands", Region::Oceania),
+    country("SOM", "SO", "Somalia", Region::Africa),
+    country("ZAF", "ZA", "South Africa", Region::Africa),
+    country("SSD", "SS", "South Sudan", Region::Africa),
+    country("ESP", "ES", "Spain", Region::Europe),
+    country("LKA", "LK", "Sri Lanka", Region::Asia),
+    country("SDN", "SD", "Sudan", Region::Africa),
+    country("SUR", "SR", "Suriname", Region::Americas),
+    country("SJM", "SJ", "Svalbard and Jan Mayen", Region::Europe),
+    country("SWE", "SE", "Sweden", Region::Europe),
+    country("CHE", "CH", "Switzerland", Region::Europe),
+    country("SYR", "SY", "Syria", Region::Asia),
+    country("TWN", "TW", "Taiwan", Region::Asia),
+    country("TJK", "TJ", "Tajikistan", Region::Asia),
+    country("TZA", "TZ", "Tanzania", Region::Africa),
+    country("THA", "TH", "Thailand", Region::Asia),
+    country("TLS", "TL", "Timor-Leste", Region::Asia),
+    country("TGO", "TG", "Togo", Region::Africa),
+    country("TKL", "TK", "Tokelau", Region::Oceania),
+    country("TON", "TO", "Tonga", Region::Oceania),
+    country("TTO", "TT", "Trinidad and Tobago", Region::Americas),
+    country("TUN", "TN", "Tunisia", Region::Africa),
+    country("TUR", "TR", "Türkiye", Region::Asia),
+    country("TKM", "TM", "Turkmenistan", Region::Asia),
+    country("TCA", "TC", "Turks and Caicos Islands", Region::Americas),
+    country("TUV", "TV", "Tuvalu", Region::Oceania),
+    country("UGA", "UG", "Uganda", Region::Africa),
+    country("UKR", "UA", "Ukraine", Region::Europe),
+    country("ARE", "AE", "United Arab Emirates", Region::Asia),
+    country("GBR", "GB", "United Kingdom", Region::Europe),
+    country("USA", "US", "United States", Region::Americas),
+    country("URY", "UY", "Uruguay", Region::Americas),
+    country("UZB", "UZ", "Uzbekistan", Region::Asia),
+    country("VUT", "VU", "Vanuatu", Region::Oceania),
+    country("VEN", "VE", "Venezuela", Region::Americas),
+    country("VNM", "VN", "Vietnam", Region::Asia),
+    country("VGB", "VG", "British Virgin Islands", Region::Americas),
+    country("VIR", "VI", "US Virgin Islands", Region::Americas),
+    country("WLF", "WF", "Wallis and Futuna", Region::Oceania),
+    country("ESH", "EH", "Western Sahara", Region::Africa),
+    country("YEM", "YE", "Yemen", Region::Asia),
+    country("ZMB", "ZM", "Zambia", Region::Africa),
+    country("ZWE", "ZW", "Zimbabwe", Region::Africa),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_countries() {
+        let argentina = Country::find("arg").unwrap();
+        assert_eq!(argentina.alpha2, "AR");
+        assert_eq!(argentina.region, Region::Americas);
+        assert_eq!(argentina.to_string(), "🇦🇷 Argentina");
+        assert_eq!(Country::find("CI").unwrap().name, "Côte d'Ivoire");
+        assert_eq!(Country::find("XXX"), None);
+
+        let alpha3: HashSet<_> = COUNTRIES.iter().map(|c| c.alpha3).collect();
+        let alpha2: HashSet<_> = COUNTRIES.iter().map(|c| c.alpha2).collect();
+        assert_eq!(alpha3.len(), COUNTRIES.len());
+        assert_eq!(alpha2.len(), COUNTRIES.len());
+        assert!(COUNTRIES
+            .iter()
+            .all(|c| c.alpha3.len() == 3 && c.alpha2.len() == 2 && c.flag().chars().count() == 2));
+    }
+}
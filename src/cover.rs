@@ -0,0 +1,497 @@
+use crate::radiooo::{RadioooClient, RadioooError, Track};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::Print;
+use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbImage};
+use log::warn;
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::StatefulWidget};
+use serde::Deserialize;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::{env, fmt::Write};
+use thiserror::Error;
+
+/// Kitty accepts at most 4096 bytes of payload per escape sequence.
+const KITTY_CHUNK_SIZE: usize = 4096;
+/// Deletes every image placed with the kitty protocol.
+const KITTY_DELETE: &str = "\x1b_Ga=d,d=A,q=2\x1b\\";
+/// Cell size assumed when the terminal does not report its size in pixels.
+const DEFAULT_CELL_SIZE: (u16, u16) = (10, 20);
+
+/// Error returned while fetching a cover.
+#[derive(Debug, Error)]
+pub enum CoverError {
+    #[error("the track has no cover")]
+    Missing,
+    #[error(transparent)]
+    Api(#[from] RadioooError),
+    #[error("cover cache: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to decode the cover: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Covers fetched from the CDN, cached on disk by track uuid.
+#[derive(Debug, Clone)]
+pub struct CoverStore {
+    dir: PathBuf,
+    client: RadioooClient,
+}
+
+impl CoverStore {
+    /// Constructs a new instance of [`CoverStore`] caching its files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, client: RadioooClient) -> Self {
+        Self {
+            dir: dir.into(),
+            client,
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/radiooooo/covers`.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("radiooooo").join("covers"))
+    }
+
+    /// Returns the cover of `track`, downloading it on the first call.
+    pub async fn get(&self, track: &Track) -> Result<Cover, CoverError> {
+        let image = track
+            .cover
+            .as_ref()
+            .or(track.image.as_ref())
+            .ok_or(CoverError::Missing)?;
+        let extension = Path::new(image.filename())
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg");
+        let path = self.dir.join(format!("{}.{}", track.uuid, extension));
+
+        if let Ok(bytes) = tokio::fs::read(&path).await {
+            match image::load_from_memory(&bytes) {
+                Ok(decoded) => return Ok(Cover::new(track.uuid.clone(), decoded)),
+                Err(err) => {
+                    warn!(
+                        "fetching the unreadable cover {} again: {}",
+                        path.display(),
+                        err
+                    );
+                    let _ = tokio::fs::remove_file(&path).await;
+                }
+            }
+        }
+        let bytes = self.client.get_cover(image).await?;
+        // only cached once it decodes
+        let decoded = image::load_from_memory(&bytes)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, &bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(Cover::new(track.uuid.clone(), decoded))
+    }
+}
+
+/// How the cover is drawn in the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphicsProtocol {
+    /// Kitty graphics protocol, also spoken by WezTerm and Ghostty.
+    Kitty,
+    Sixel,
+    /// Two pixels per cell with unicode half blocks, works everywhere.
+    #[default]
+    HalfBlocks,
+}
+
+impl GraphicsProtocol {
+    /// Guesses the best protocol supported by the terminal from its environment.
+    pub fn detect() -> Self {
+        let var = |name: &str| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        let term_program = var("TERM_PROGRAM");
+        if env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || term_program == "WezTerm"
+            || term_program == "ghostty"
+        {
+            GraphicsProtocol::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || term_program == "iTerm.app"
+        {
+            GraphicsProtocol::Sixel
+        } else {
+            GraphicsProtocol::HalfBlocks
+        }
+    }
+}
+
+/// A decoded cover, along with its rendering for the last drawn area.
+#[derive(Debug, Clone)]
+pub struct Cover {
+    pub uuid: String,
+    pub image: DynamicImage,
+    rendered: Option<(Rect, GraphicsProtocol, Rendered)>,
+    /// Whether the last frame left room for an escape sequence, see [`Overlay`].
+    placed: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Rendered {
+    HalfBlocks(RgbImage),
+    Escape(String),
+}
+
+impl Cover {
+    /// Constructs a new instance of [`Cover`].
+    pub fn new(uuid: String, image: DynamicImage) -> Self {
+        Self {
+            uuid,
+            image,
+            rendered: None,
+            placed: false,
+        }
+    }
+
+    /// Renders the cover for `area`, reusing the previous rendering if possible
+    /// since encoding an image on every frame is expensive.
+    fn render(&mut self, area: Rect, protocol: GraphicsProtocol) -> &Rendered {
+        let stale = !matches!(&self.rendered, Some((a, p, _)) if *a == area && *p == protocol);
+        if stale {
+            let rendered = match protocol {
+                GraphicsProtocol::HalfBlocks => Rendered::HalfBlocks(
+                    self.image
+                        .resize(
+                            area.width as u32,
+                            area.height as u32 * 2,
+                            FilterType::Triangle,
+                        )
+                        .to_rgb8(),
+                ),
+                GraphicsProtocol::Kitty => Rendered::Escape(encode_kitty(&self.image, area)),
+                GraphicsProtocol::Sixel => {
+                    let (cell_width, cell_height) = cell_size();
+                    let image = self
+                        .image
+                        .resize(
+                            (area.width * cell_width) as u32,
+                            (area.height * cell_height) as u32,
+                            FilterType::Triangle,
+                        )
+                        .to_rgb8();
+                    Rendered::Escape(encode_sixel(&image))
+                }
+            };
+            self.rendered = Some((area, protocol, rendered));
+        }
+        &self.rendered.as_ref().expect("rendered above").2
+    }
+}
+
+/// Draws a [`Cover`] with the given [`GraphicsProtocol`].
+#[derive(Debug, Clone, Copy)]
+pub struct CoverWidget {
+    protocol: GraphicsProtocol,
+}
+
+impl CoverWidget {
+    /// Constructs a new instance of [`CoverWidget`].
+    pub fn new(protocol: GraphicsProtocol) -> Self {
+        Self { protocol }
+    }
+}
+
+impl StatefulWidget for CoverWidget {
+    type State = Cover;
+
+    fn render(self, area: Rect, buf: &mut Buffer, cover: &mut Cover) {
+        if area.is_empty() {
+            return;
+        }
+        match cover.render(area, self.protocol) {
+            Rendered::HalfBlocks(image) => {
+                for y in 0..area.height.min((image.height() as u16).div_ceil(2)) {
+                    for x in 0..area.width.min(image.width() as u16) {
+                        let top = image.get_pixel(x as u32, y as u32 * 2);
+                        let bottom = image
+                            .get_pixel_checked(x as u32, y as u32 * 2 + 1)
+                            .unwrap_or(top);
+                        buf.get_mut(area.x + x, area.y + y)
+                            .set_symbol("▀")
+                            .set_fg(Color::Rgb(top[0], top[1], top[2]))
+                            .set_bg(Color::Rgb(bottom[0], bottom[1], bottom[2]));
+                    }
+                }
+            }
+            Rendered::Escape(_) => {
+                // the terminal draws the image over these cells, the sequence
+                // is written by the overlay once the frame is flushed
+                for y in area.top()..area.bottom() {
+                    for x in area.left()..area.right() {
+                        buf.get_mut(x, y).reset();
+                        buf.get_mut(x, y).set_skip(true);
+                    }
+                }
+                cover.placed = true;
+            }
+        }
+    }
+}
+
+/// Writes the covers drawn with escape sequences, once a frame is flushed.
+///
+/// A cell holding the whole sequence would make ratatui redraw as many cells
+/// as the sequence is long on every frame, so [`CoverWidget`] only skips the
+/// cells and the image is written here when it changes.
+#[derive(Debug, Default)]
+pub struct Overlay {
+    /// Cover on screen: its uuid, area and protocol.
+    shown: Option<(String, Rect, GraphicsProtocol)>,
+    /// Size of the screen, a resize clears it.
+    screen: Rect,
+}
+
+impl Overlay {
+    /// Places the cover drawn by the last frame, deleting the previous one
+    /// when it changed or when no cover was drawn.
+    pub fn write(
+        &mut self,
+        out: &mut impl io::Write,
+        screen: Rect,
+        cover: Option<&mut Cover>,
+    ) -> io::Result<()> {
+        let resized = screen != self.screen;
+        self.screen = screen;
+        let placed = cover.and_then(|cover| {
+            if !std::mem::take(&mut cover.placed) {
+                return None;
+            }
+            match &cover.rendered {
+                Some((area, protocol, Rendered::Escape(sequence))) => {
+                    Some(((cover.uuid.clone(), *area, *protocol), sequence.as_str()))
+                }
+                _ => None,
+            }
+        });
+        let shown = placed.as_ref().map(|(shown, _)| shown.clone());
+        if shown == self.shown && !resized {
+            return Ok(());
+        }
+        if let Some((_, _, GraphicsProtocol::Kitty)) = self.shown {
+            queue!(out, Print(KITTY_DELETE))?;
+        }
+        if let Some(((_, area, protocol), sequence)) = placed {
+            if protocol == GraphicsProtocol::Sixel {
+                // the previous image may overflow the new one
+                let blank = " ".repeat(area.width.into());
+                for y in area.top()..area.bottom() {
+                    queue!(out, MoveTo(area.x, y), Print(&blank))?;
+                }
+            }
+            queue!(out, MoveTo(area.x, area.y), Print(sequence))?;
+        }
+        self.shown = shown;
+        out.flush()
+    }
+}
+
+/// Size of a terminal cell in pixels.
+fn cell_size() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            (size.width / size.columns, size.height / size.rows)
+        }
+        _ => DEFAULT_CELL_SIZE,
+    }
+}
+
+/// Transmits the image as PNG and lets the terminal scale it to `area`.
+fn encode_kitty(image: &DynamicImage, area: Rect) -> String {
+    let mut png = Vec::new();
+    if image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .is_err()
+    {
+        return String::new();
+    }
+    let payload = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+
+    let mut sequence = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ascii");
+        if i == 0 {
+            let _ = write!(
+                sequence,
+                "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                area.width, area.height, more, chunk
+            );
+        } else {
+            let _ = write!(sequence, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    sequence
+}
+
+/// Encodes the image as sixels, quantized to a 6x6x6 color cube.
+fn encode_sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |value: u8| (value as u32 * 5 + 127) / 255;
+    let color_index = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y);
+        (level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])) as usize
+    };
+
+    let mut sequence = format!("\x1bPq\"1;1;{};{}", width, height);
+    for index in 0..216u32 {
+        let percent = |level: u32| level * 100 / 5;
+        let _ = write!(
+            sequence,
+            "#{};2;{};{};{}",
+            index,
+            percent(index / 36),
+            percent(index / 6 % 6),
+            percent(index % 6)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let mut used = [false; 216];
+        for y in band..band + rows {
+            for x in 0..width {
+                used[color_index(x, y)] = true;
+            }
+        }
+        for color in (0..216).filter(|color| used[*color]) {
+            let _ = write!(sequence, "#{}", color);
+            let mut run: Option<(char, u32)> = None;
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|dy| color_index(x, band + dy) == color)
+                    .fold(0u8, |bits, dy| bits | 1 << dy);
+                let sixel = char::from(63 + bits);
+                run = match run {
+                    Some((c, n)) if c == sixel => Some((c, n + 1)),
+                    Some((c, n)) => {
+                        push_sixel_run(&mut sequence, c, n);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            if let Some((c, n)) = run {
+                push_sixel_run(&mut sequence, c, n);
+            }
+            // back to the start of the band for the next color
+            sequence.push('$');
+        }
+        sequence.push('-');
+    }
+    sequence.push_str("\x1b\\");
+    sequence
+}
+
+fn push_sixel_run(sequence: &mut String, sixel: char, count: u32) {
+    if count > 3 {
+        let _ = write!(sequence, "!{}{}", count, sixel);
+    } else {
+        sequence.extend(std::iter::repeat_n(sixel, count as usize));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiooo::tests::sample_track;
+    use image::{ImageBuffer, Rgb};
+    use std::fs;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn red_square() -> Vec<u8> {
+        let image: RgbImage = ImageBuffer::from_pixel(4, 4, Rgb([255, 0, 0]));
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[tokio::test]
+    async fn test_cover_is_cached_on_disk() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/cover/ARG/1970/380ca57f-188e-4795-9b17-f1721a7e8188.jpg",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(red_square()))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .cover_base_url(server.uri())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = CoverStore::new(dir.path(), client);
+
+        let track = sample_track();
+        let cover = store.get(&track).await.unwrap();
+        assert_eq!(cover.image.width(), 4);
+        assert!(dir.path().join(format!("{}.jpg", track.uuid)).exists());
+        // served from the disk the second time
+        store.get(&track).await.unwrap();
+
+        // a truncated file is fetched again
+        let cached = dir.path().join(format!("{}.jpg", track.uuid));
+        fs::write(&cached, &red_square()[..10]).unwrap();
+        assert_eq!(store.get(&track).await.unwrap().image.width(), 4);
+        assert_eq!(fs::read(&cached).unwrap(), red_square());
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(4, 4, Rgb([255, 0, 0])));
+        let mut cover = Cover::new(String::from("uuid"), image);
+        let area = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::empty(area);
+        CoverWidget::new(GraphicsProtocol::HalfBlocks).render(area, &mut buf, &mut cover);
+        assert_eq!(buf.get(0, 0).symbol(), "▀");
+        assert_eq!(buf.get(1, 0).fg, Color::Rgb(255, 0, 0));
+    }
+
+    #[test]
+    fn test_escape_sequences() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(4, 6, Rgb([0, 0, 255])));
+        let sixel = encode_sixel(&image.to_rgb8());
+        assert!(sixel.starts_with("\x1bPq\"1;1;4;6"));
+        assert!(sixel.ends_with("#5!4~$-\x1b\\"));
+
+        let mut cover = Cover::new(String::from("uuid"), image);
+        let area = Rect::new(0, 0, 3, 2);
+        let mut buf = Buffer::empty(area);
+        CoverWidget::new(GraphicsProtocol::Kitty).render(area, &mut buf, &mut cover);
+        assert!(buf.get(0, 0).skip && buf.get(2, 1).skip);
+        assert_eq!(buf.get(0, 0).symbol(), " ");
+
+        // written once, then deleted when the panel goes away
+        let screen = Rect::new(0, 0, 80, 24);
+        let mut overlay = Overlay::default();
+        let mut out = Vec::new();
+        overlay.write(&mut out, screen, Some(&mut cover)).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert!(written.contains("\x1b_Ga=T,f=100"));
+        assert!(!written.contains(KITTY_DELETE));
+
+        let mut out = Vec::new();
+        CoverWidget::new(GraphicsProtocol::Kitty).render(area, &mut buf, &mut cover);
+        overlay.write(&mut out, screen, Some(&mut cover)).unwrap();
+        assert!(out.is_empty());
+
+        overlay.write(&mut out, screen, Some(&mut cover)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), KITTY_DELETE);
+    }
+}
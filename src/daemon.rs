@@ -0,0 +1,685 @@
+use crate::app::{App, PlayState};
+use crate::event::{Control, Event};
+use crate::mpris::MprisServer;
+use crate::player::{Player, PlayerError, PlayerResult};
+use crate::radiooo::{ExploreRequest, Track};
+use log::{debug, error, info};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{env, fs};
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+use tokio::net::{UnixListener, UnixStream as AsyncUnixStream};
+use tokio::signal;
+use tokio::sync::{mpsc, oneshot};
+
+/// Invalid JSON received.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command was understood but failed, e.g. no track was available.
+const COMMAND_FAILED: i64 = -32000;
+
+/// Error returned while running or reaching the daemon.
+#[derive(Debug, Error)]
+pub enum DaemonError {
+    #[error("failed to reach the daemon at {path}: {source}")]
+    Connect {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("a daemon is already listening on {0}")]
+    AlreadyRunning(PathBuf),
+    #[error("daemon connection: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid daemon message: {0}")]
+    Protocol(#[from] serde_json::Error),
+    #[error("{0}")]
+    Rpc(RpcError),
+}
+
+impl From<DaemonError> for PlayerError {
+    fn from(err: DaemonError) -> Self {
+        Self(err.to_string())
+    }
+}
+
+/// JSON-RPC 2.0 request, one per line.
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// JSON-RPC 2.0 response, one per line.
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    jsonrpc: String,
+    id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: String::from("2.0"),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// Error member of a JSON-RPC response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Error)]
+#[error("daemon error {code}: {message}")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// How long a client waits for the daemon to take or answer a request.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between two reads of the playback position, which decides when
+/// a track is scrobbled.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Playback state in a [`Status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Playback {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// Result of every method: the state of the daemon once the command is done.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub playback: Playback,
+    pub track: Option<Track>,
+    pub volume: u16,
+    pub muted: bool,
+    /// Seconds.
+    pub position: Option<f64>,
+    /// Seconds.
+    pub duration: Option<f64>,
+    pub station: Option<ExploreRequest>,
+    /// Last failure, e.g. of the automatic switch to the next track.
+    pub error: Option<String>,
+}
+
+impl Status {
+    pub fn of(app: &App) -> Self {
+        let (playback, track) = match &app.play_state {
+            PlayState::Playing(track) => (Playback::Playing, Some(track.clone())),
+            PlayState::Paused(track) => (Playback::Paused, Some(track.clone())),
+            PlayState::Stopped => (Playback::Stopped, None),
+        };
+        Self {
+            playback,
+            track,
+            volume: app.volume,
+            muted: app.muted,
+            position: app.position.map(|d| d.as_secs_f64()),
+            duration: app.duration.map(|d| d.as_secs_f64()),
+            station: app.station.clone(),
+            error: app.error.clone(),
+        }
+    }
+
+    /// The state the [`App`] of a client should mirror.
+    pub fn play_state(&self) -> PlayState {
+        match (self.playback, &self.track) {
+            (Playback::Playing, Some(track)) => PlayState::Playing(track.clone()),
+            (Playback::Paused, Some(track)) => PlayState::Paused(track.clone()),
+            _ => PlayState::Stopped,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct VolumeParams {
+    volume: u16,
+}
+
+#[derive(Deserialize)]
+struct MutedParams {
+    muted: bool,
+}
+
+#[derive(Deserialize)]
+struct SeekParams {
+    /// Seconds, backward if negative.
+    offset: f64,
+}
+
+#[derive(Deserialize)]
+struct PositionParams {
+    /// Seconds from the start of the track.
+    position: f64,
+}
+
+#[derive(Deserialize)]
+struct TrackParams {
+    track: Track,
+}
+
+#[derive(Deserialize)]
+struct LoadParams {
+    url: String,
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+/// Runs `method` on `app` and returns the resulting [`Status`].
+///
+/// A failure of `method` is returned as an error and kept in
+/// [`Status::error`] until a new track plays, so that clients polling the
+/// status also see the failures of the automatic switch to the next track.
+///
+/// | method          | params                                    |
+/// |-----------------|-------------------------------------------|
+/// | `play`          | resumes, or starts the station if stopped |
+/// | `pause`         |                                           |
+/// | `stop`          |                                           |
+/// | `next`          |                                           |
+/// | `previous`      |                                           |
+/// | `set_volume`    | `{"volume": 80}`                          |
+/// | `set_muted`     | `{"muted": true}`                         |
+/// | `seek`          | `{"offset": -2.5}`, in seconds            |
+/// | `set_position`  | `{"position": 30.5}`, in seconds          |
+/// | `set_selection` | an explore request, tunes to it           |
+/// | `play_track`    | `{"track": {…}}`                          |
+/// | `load`          | `{"url": "…"}`, plays a raw stream        |
+/// | `status`        |                                           |
+/// | `quit`          | stops the daemon                          |
+fn dispatch(app: &mut App, method: &str, params_value: Value) -> Result<Value, RpcError> {
+    let previous_error = app.error.take();
+    let previous_track = app.play_state.track().map(|track| track._id.clone());
+    match method {
+        "play" => app.control(Control::Play),
+        "pause" => app.control(Control::Pause),
+        "stop" => app.stop(),
+        "next" => app.next_track(),
+        "previous" => app.previous_track(),
+        "set_volume" => app.set_volume(params::<VolumeParams>(params_value)?.volume),
+        "set_muted" => {
+            if app.muted != params::<MutedParams>(params_value)?.muted {
+                app.toggle_mute();
+            }
+        }
+        "seek" => app.seek(params::<SeekParams>(params_value)?.offset),
+        "set_position" => app.set_position(params::<PositionParams>(params_value)?.position),
+        "set_selection" => app.tune(params(params_value)?),
+        "play_track" => app.play_track(params::<TrackParams>(params_value)?.track),
+        "load" => {
+            let url = params::<LoadParams>(params_value)?.url;
+            if let Err(err) = app.player.load(&url) {
+                app.report_error(err);
+            }
+        }
+        "status" => app.tick(),
+        "quit" => app.quit(),
+        _ => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {method}"),
+            ))
+        }
+    }
+    if let Some(err) = &app.error {
+        return Err(RpcError::new(COMMAND_FAILED, err.clone()));
+    }
+    if app.play_state.track().map(|track| &track._id) == previous_track.as_ref() {
+        app.error = previous_error;
+    }
+    Ok(json!(Status::of(app)))
+}
+
+/// A request waiting for the main loop, with where to send its response.
+type Pending = (Request, oneshot::Sender<Response>);
+
+/// Owns the player and serves JSON-RPC requests on a Unix socket.
+#[derive(Debug)]
+pub struct Daemon {
+    listener: UnixListener,
+    path: PathBuf,
+    mpris: Option<MprisServer>,
+}
+
+impl Daemon {
+    /// `$XDG_RUNTIME_DIR/radiooooo.sock`, or a per user socket in the
+    /// temporary directory.
+    pub fn default_path() -> PathBuf {
+        dirs::runtime_dir()
+            .map(|dir| dir.join("radiooooo.sock"))
+            .unwrap_or_else(|| {
+                let user = env::var("USER").unwrap_or_else(|_| String::from("user"));
+                env::temp_dir().join(format!("radiooooo-{user}.sock"))
+            })
+    }
+
+    /// Listens on `path`, replacing the socket left by a daemon that died.
+    pub fn bind(path: impl Into<PathBuf>) -> Result<Self, DaemonError> {
+        let path = path.into();
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(DaemonError::AlreadyRunning(path));
+            }
+            fs::remove_file(&path)?;
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        info!("listening on {}", path.display());
+        Ok(Self {
+            listener,
+            path,
+            mpris: None,
+        })
+    }
+
+    /// Publishes the state of the player through `server` as it changes.
+    pub fn mpris(mut self, server: MprisServer) -> Self {
+        self.mpris = Some(server);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serves the clients until `quit` is requested or the process is
+    /// interrupted, moving to the next track when `events` reports the end of
+    /// one and applying the [`Control`]s and API responses it carries.
+    pub async fn run(
+        &self,
+        app: &mut App,
+        events: &mut mpsc::UnboundedReceiver<Event>,
+    ) -> Result<(), DaemonError> {
+        let (sender, mut requests) = mpsc::unbounded_channel::<Pending>();
+        let mut progress = tokio::time::interval(PROGRESS_INTERVAL);
+        while app.running {
+            tokio::select! {
+                _ = progress.tick() => app.tick(),
+                accepted = self.listener.accept() => {
+                    let (stream, _) = accepted?;
+                    tokio::spawn(serve(stream, sender.clone()));
+                }
+                Some((request, reply)) = requests.recv() => {
+                    debug!("daemon request: {} {}", request.method, request.params);
+                    let result = dispatch(app, &request.method, request.params);
+                    let _ = reply.send(Response::new(request.id.unwrap_or_default(), result));
+                }
+                event = events.recv() => match event {
+                    Some(Event::TrackEnded) => app.next_track(),
+                    Some(Event::TrackFailed) => {
+                        app.track_failed();
+                        app.next_track();
+                    }
+                    Some(Event::Control(control)) => app.control(control),
+                    Some(Event::Response(response)) => app.handle_response(*response),
+                    Some(_) => {}
+                    None => break,
+                },
+                _ = signal::ctrl_c() => break,
+            }
+            if let Some(mpris) = &self.mpris {
+                if let Err(err) = mpris.update(app).await {
+                    error!("failed to update MPRIS: {}", err);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Forwards the requests of one client to the main loop.
+async fn serve(stream: AsyncUnixStream, sender: mpsc::UnboundedSender<Pending>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = AsyncBufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let notification = request.id.is_none();
+                let (reply, response) = oneshot::channel();
+                if sender.send((request, reply)).is_err() {
+                    break;
+                }
+                match response.await {
+                    Ok(_) if notification => continue,
+                    Ok(response) => response,
+                    Err(_) => break,
+                }
+            }
+            Err(err) => Response::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            ),
+        };
+        let mut line = serde_json::to_vec(&response).expect("responses are serializable");
+        line.push(b'\n');
+        if writer.write_all(&line).await.is_err() {
+            break;
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+/// Blocking client of a [`Daemon`], each call gives up after
+/// [`CLIENT_TIMEOUT`].
+///
+/// It is also a [`Player`], so that an [`App`] can drive the daemon as it
+/// would drive mpv. Clones share the same connection and the last status
+/// received, which answers [`Player::position`] and [`Player::duration`].
+#[derive(Clone)]
+pub struct DaemonClient {
+    connection: Arc<Mutex<Connection>>,
+    last_status: Arc<Mutex<Option<Status>>>,
+}
+
+impl std::fmt::Debug for DaemonClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DaemonClient").finish_non_exhaustive()
+    }
+}
+
+impl DaemonClient {
+    pub fn connect(path: impl AsRef<Path>) -> Result<Self, DaemonError> {
+        let path = path.as_ref();
+        let writer = UnixStream::connect(path).map_err(|source| DaemonError::Connect {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        writer.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        writer.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self {
+            connection: Arc::new(Mutex::new(Connection {
+                reader,
+                writer,
+                next_id: 0,
+            })),
+            last_status: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Calls `method` and waits for its result.
+    pub fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: impl Serialize,
+    ) -> Result<T, DaemonError> {
+        let mut connection = self.connection.lock().unwrap();
+        connection.next_id += 1;
+        let id = connection.next_id;
+        let request = Request {
+            jsonrpc: String::from("2.0"),
+            id: Some(id.into()),
+            method: method.to_string(),
+            params: serde_json::to_value(params)?,
+        };
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        connection.writer.write_all(&line)?;
+
+        // the responses to the calls that timed out come first
+        let response = loop {
+            let mut line = String::new();
+            if connection.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let response: Response = serde_json::from_str(&line)?;
+            if response.id == id {
+                break response;
+            }
+        };
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(DaemonError::Rpc(error)),
+            (Some(result), None) => Ok(serde_json::from_value(result)?),
+            (None, None) => Ok(serde_json::from_value(Value::Null)?),
+        }
+    }
+
+    /// Calls `method` and keeps the status it returns.
+    pub fn execute(&self, method: &str, params: Value) -> Result<Status, DaemonError> {
+        let status: Status = self.call(method, params)?;
+        *self.last_status.lock().unwrap() = Some(status.clone());
+        Ok(status)
+    }
+
+    pub fn status(&self) -> Result<Status, DaemonError> {
+        self.execute("status", Value::Null)
+    }
+
+    fn command(&self, method: &str, params: Value) -> PlayerResult<()> {
+        self.execute(method, params)?;
+        Ok(())
+    }
+}
+
+impl Player for DaemonClient {
+    fn load(&mut self, url: &str) -> PlayerResult<()> {
+        self.command("load", json!({ "url": url }))
+    }
+
+    fn pause(&mut self) -> PlayerResult<()> {
+        self.command("pause", Value::Null)
+    }
+
+    fn resume(&mut self) -> PlayerResult<()> {
+        self.command("play", Value::Null)
+    }
+
+    fn stop(&mut self) -> PlayerResult<()> {
+        self.command("stop", Value::Null)
+    }
+
+    fn set_volume(&mut self, volume: u16) -> PlayerResult<()> {
+        self.command("set_volume", json!({ "volume": volume }))
+    }
+
+    fn set_muted(&mut self, muted: bool) -> PlayerResult<()> {
+        self.command("set_muted", json!({ "muted": muted }))
+    }
+
+    fn position(&self) -> Option<Duration> {
+        let position = self.last_status.lock().unwrap().as_ref()?.position?;
+        Some(Duration::from_secs_f64(position))
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        let duration = self.last_status.lock().unwrap().as_ref()?.duration?;
+        Some(Duration::from_secs_f64(duration))
+    }
+
+    fn seek(&mut self, offset: f64) -> PlayerResult<()> {
+        self.command("seek", json!({ "offset": offset }))
+    }
+
+    fn seek_to(&mut self, position: f64) -> PlayerResult<()> {
+        self.command("set_position", json!({ "position": position }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::FakePlayer;
+    use crate::radiooo::tests::{sample_track, TRACK_JSON};
+    use crate::radiooo::{Mood, RadioooClient};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_errors_last_until_a_new_track() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.report_error("no track was found for current setting");
+        for _ in 0..2 {
+            let status: Status =
+                serde_json::from_value(dispatch(&mut app, "status", Value::Null).unwrap()).unwrap();
+            assert!(status.error.is_some());
+        }
+        dispatch(&mut app, "set_volume", json!({ "volume": 80 })).unwrap();
+        assert!(app.error.is_some());
+
+        let mut track = sample_track();
+        track.links.mpeg = String::from("https://example.com/track.mp3");
+        let status: Status = serde_json::from_value(
+            dispatch(&mut app, "play_track", json!({ "track": track })).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(status.error, None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_daemon() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(TRACK_JSON, "application/json"))
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("radiooooo.sock");
+        let daemon = Daemon::bind(&socket).unwrap();
+        assert!(matches!(
+            Daemon::bind(&socket),
+            Err(DaemonError::AlreadyRunning(_))
+        ));
+        let (sender, mut events) = mpsc::unbounded_channel();
+        app.events = Some(sender);
+        let running = tokio::spawn(async move {
+            daemon.run(&mut app, &mut events).await.unwrap();
+        });
+
+        let remote = socket.clone();
+        let seeked = player.clone();
+        tokio::task::spawn_blocking(move || {
+            let daemon = DaemonClient::connect(&remote).unwrap();
+            assert_eq!(daemon.status().unwrap().playback, Playback::Stopped);
+
+            let station = ExploreRequest::new(
+                vec![Mood::Fast],
+                vec!["1970".parse().unwrap()],
+                vec!["ARG".parse().unwrap()],
+            );
+            // the track is requested in the background
+            let status: Status = daemon.call("set_selection", &station).unwrap();
+            assert_eq!(status.station, Some(station));
+            for _ in 0..100 {
+                if daemon.status().unwrap().playback == Playback::Playing {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(daemon.status().unwrap().playback, Playback::Playing);
+            daemon
+                .call::<Status>("seek", json!({ "offset": 2.5 }))
+                .unwrap();
+            assert_eq!(seeked.state().position, Some(Duration::from_millis(2500)));
+            daemon
+                .call::<Status>("set_position", json!({ "position": 30.5 }))
+                .unwrap();
+            assert_eq!(seeked.state().position, Some(Duration::from_millis(30500)));
+            let status: Status = daemon.call("set_volume", json!({ "volume": 80 })).unwrap();
+            assert_eq!(status.volume, 80);
+            assert!(matches!(
+                daemon.call::<Status>("set_volume", json!({ "volume": "loud" })),
+                Err(DaemonError::Rpc(RpcError {
+                    code: INVALID_PARAMS,
+                    ..
+                }))
+            ));
+            assert!(matches!(
+                daemon.call::<Status>("dance", Value::Null),
+                Err(DaemonError::Rpc(RpcError {
+                    code: METHOD_NOT_FOUND,
+                    ..
+                }))
+            ));
+
+            // the interface of a client mirrors the daemon
+            let mut app = App::remote(daemon.clone(), client);
+            assert!(matches!(app.play_state, PlayState::Playing(_)));
+            assert_eq!(app.history_cursor, Some(0));
+            app.playpause();
+            assert_eq!(daemon.status().unwrap().playback, Playback::Paused);
+            app.increment_volume();
+            assert_eq!(daemon.status().unwrap().volume, 85);
+
+            // the status is polled in the background, one call at a time
+            let (sender, mut events) = mpsc::unbounded_channel();
+            app.events = Some(sender);
+            app.tick();
+            app.tick();
+            match events.blocking_recv() {
+                Some(Event::Response(response)) => app.handle_response(*response),
+                event => panic!("unexpected event {event:?}"),
+            }
+            assert!(events.try_recv().is_err());
+            assert_eq!(app.volume, 85);
+            assert_eq!(app.player.duration(), app.duration);
+
+            daemon.call::<Status>("quit", Value::Null).unwrap();
+        })
+        .await
+        .unwrap();
+
+        running.await.unwrap();
+        assert_eq!(player.state().volume, 85);
+        assert!(player.state().paused);
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::radiooo::{Links, RadioooClient, RadioooError, Track};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use id3::frame::{ExtendedText, Picture, PictureType};
+use id3::{TagLike, Version};
+use log::warn;
+use ogg::{PacketReader, PacketWriteEndInfo, PacketWriter};
+use serde::Deserialize;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+
+/// Longest file stem written, in bytes, leaving room for the extension under
+/// the usual 255 bytes limit.
+const MAX_STEM_LEN: usize = 240;
+/// Longest wait for the next chunk of an audio stream.
+const STREAM_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Vendor string of the comment header when the original one is unreadable.
+const VORBIS_VENDOR: &str = "radiooooo";
+
+/// Error returned while downloading a track.
+#[derive(Debug, Error)]
+pub enum DownloadError {
+    #[error(transparent)]
+    Api(#[from] RadioooError),
+    #[error("failed to write {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to tag the mp3: {0}")]
+    Id3(#[from] id3::Error),
+    #[error("failed to tag the ogg: {0}")]
+    Ogg(String),
+}
+
+/// Encoding of a track, one per stream of its [`Links`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// MP3, tagged with ID3v2.4.
+    #[default]
+    #[serde(alias = "mp3")]
+    Mpeg,
+    /// Ogg Vorbis, tagged with Vorbis comments.
+    Ogg,
+}
+
+impl AudioFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Mpeg => "mp3",
+            AudioFormat::Ogg => "ogg",
+        }
+    }
+
+    /// The stream of `links` in this format.
+    pub fn url(self, links: &Links) -> &str {
+        match self {
+            AudioFormat::Mpeg => &links.mpeg,
+            AudioFormat::Ogg => &links.ogg,
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AudioFormat::Mpeg => "mpeg",
+            AudioFormat::Ogg => "ogg",
+        })
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mpeg" | "mp3" => Ok(AudioFormat::Mpeg),
+            "ogg" | "vorbis" => Ok(AudioFormat::Ogg),
+            _ => Err(format!("unknown audio format: {s}")),
+        }
+    }
+}
+
+/// Saves tracks as tagged audio files, named `Artist - Title.ext`.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    dir: PathBuf,
+    client: RadioooClient,
+    format: AudioFormat,
+}
+
+impl Downloader {
+    /// Constructs a new instance of [`Downloader`] saving mp3 files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>, client: RadioooClient) -> Self {
+        Self {
+            dir: dir.into(),
+            client,
+            format: AudioFormat::default(),
+        }
+    }
+
+    pub fn format(mut self, format: AudioFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// `$XDG_MUSIC_DIR/radiooooo`, or `~/Music/radiooooo`.
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::audio_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join("Music")))
+            .map(|dir| dir.join("radiooooo"))
+    }
+
+    /// Where `track` is saved.
+    pub fn path(&self, track: &Track) -> PathBuf {
+        let mut stem = sanitize(&format!("{} - {}", track.artist, track.title));
+        if stem.len() > MAX_STEM_LEN {
+            let end = (0..=MAX_STEM_LEN)
+                .rev()
+                .find(|&end| stem.is_char_boundary(end))
+                .unwrap_or(0);
+            stem.truncate(end);
+        }
+        self.dir
+            .join(format!("{}.{}", stem.trim_end(), self.format.extension()))
+    }
+
+    /// Downloads and tags `track`, requesting fresh links if its own have expired.
+    pub async fn download(&self, track: &Track) -> Result<PathBuf, DownloadError> {
+        let fresh;
+        let track = if track.links.is_expired() {
+            fresh = self.client.get_track_by_id(&track._id).await?;
+            &fresh
+        } else {
+            track
+        };
+        let stream = self
+            .client
+            .get_stream(self.format.url(&track.links))
+            .await?;
+        let cover = match track.cover.as_ref().or(track.image.as_ref()) {
+            Some(image) => match self.client.get_cover(image).await {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    warn!("saving {} without its cover: {}", track._id, err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let path = self.path(track);
+        let io_error = |source| DownloadError::Io {
+            path: path.clone(),
+            source,
+        };
+        fs::create_dir_all(&self.dir).map_err(io_error)?;
+        // written next to the destination, so that an interrupted download
+        // never looks like a complete one
+        let part = path.with_extension("part");
+        match self.format {
+            AudioFormat::Mpeg => {
+                save_stream(stream, &part).await?;
+                id3_tag(track, cover).write_to_path(&part, Version::Id3v24)?;
+            }
+            AudioFormat::Ogg => {
+                // the comment header is rewritten while copying the pages
+                let raw = path.with_extension("download");
+                save_stream(stream, &raw).await?;
+                let comments = vorbis_comments(track, cover.as_deref());
+                let tagged = File::open(&raw).and_then(|audio| {
+                    let out = BufWriter::new(File::create(&part)?);
+                    Ok(tag_ogg(BufReader::new(audio), out, &comments))
+                });
+                let _ = fs::remove_file(&raw);
+                tagged
+                    .map_err(io_error)??
+                    .into_inner()
+                    .map_err(|err| io_error(err.into_error()))?;
+            }
+        }
+        fs::rename(&part, &path).map_err(io_error)?;
+        Ok(path)
+    }
+}
+
+/// Writes the body of `stream` to `path` as it arrives.
+async fn save_stream(mut stream: reqwest::Response, path: &Path) -> Result<(), DownloadError> {
+    let io_error = |source| DownloadError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut file = tokio::fs::File::create(path).await.map_err(io_error)?;
+    loop {
+        let chunk = tokio::time::timeout(STREAM_READ_TIMEOUT, stream.chunk())
+            .await
+            .map_err(|_| {
+                io_error(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the stream stalled",
+                ))
+            })?
+            .map_err(RadioooError::from)?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        file.write_all(&chunk).await.map_err(io_error)?;
+    }
+    file.flush().await.map_err(io_error)
+}
+
+/// Replaces the characters most filesystems reject.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_start_matches(['.', ' '])
+        .to_string()
+}
+
+/// Fields shared by both tag formats, empty ones skipped.
+fn text_fields(track: &Track) -> Vec<(&'static str, &str)> {
+    [
+        ("TITLE", Some(track.title.as_str())),
+        ("ARTIST", Some(track.artist.as_str())),
+        ("ALBUM", track.album.as_deref()),
+        ("DATE", Some(track.year.as_str())),
+        ("COUNTRY", Some(track.country.as_str())),
+        ("LABEL", track.label.as_deref()),
+        ("COMPOSER", track.songwriter.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.filter(|v| !v.is_empty()).map(|v| (key, v)))
+    .collect()
+}
+
+fn id3_tag(track: &Track, cover: Option<Vec<u8>>) -> id3::Tag {
+    let mut tag = id3::Tag::new();
+    for (key, value) in text_fields(track) {
+        match key {
+            "TITLE" => tag.set_title(value),
+            "ARTIST" => tag.set_artist(value),
+            "ALBUM" => tag.set_album(value),
+            "DATE" => match value.parse() {
+                Ok(year) => tag.set_year(year),
+                Err(_) => tag.set_text("TDRC", value),
+            },
+            "LABEL" => tag.set_text("TPUB", value),
+            "COMPOSER" => tag.set_text("TCOM", value),
+            _ => {
+                tag.add_frame(ExtendedText {
+                    description: key.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+    }
+    if let Some(data) = cover {
+        tag.add_frame(Picture {
+            mime_type: image_mime(&data).to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data,
+        });
+    }
+    tag
+}
+
+/// `KEY=value` comments of an ogg file, the cover as a `METADATA_BLOCK_PICTURE`.
+fn vorbis_comments(track: &Track, cover: Option<&[u8]>) -> Vec<String> {
+    let mut comments: Vec<String> = text_fields(track)
+        .into_iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    if let Some(data) = cover {
+        // FLAC picture block, big endian
+        let mime = image_mime(data);
+        let mut block = Vec::with_capacity(data.len() + 32 + mime.len());
+        block.extend(3u32.to_be_bytes()); // front cover
+        block.extend((mime.len() as u32).to_be_bytes());
+        block.extend(mime.as_bytes());
+        block.extend(0u32.to_be_bytes()); // description
+        block.extend([0u8; 16]); // width, height, depth and colors, unknown
+        block.extend((data.len() as u32).to_be_bytes());
+        block.extend(data);
+        comments.push(format!("METADATA_BLOCK_PICTURE={}", STANDARD.encode(block)));
+    }
+    comments
+}
+
+fn image_mime(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    }
+}
+
+/// Rewrites the comment header of an Ogg Vorbis stream with `comments`.
+///
+/// Page boundaries are kept so that granule positions stay valid, only the
+/// pages holding the headers are re-laid out.
+fn tag_ogg<R: Read + Seek, W: Write>(
+    audio: R,
+    out: W,
+    comments: &[String],
+) -> Result<W, DownloadError> {
+    let ogg_error = |err: ogg::OggReadError| DownloadError::Ogg(err.to_string());
+    let mut reader = PacketReader::new(audio);
+    let mut writer = PacketWriter::new(out);
+    let mut index = 0;
+    while let Some(packet) = reader.read_packet().map_err(ogg_error)? {
+        let serial = packet.stream_serial();
+        let end = if packet.last_in_stream() {
+            PacketWriteEndInfo::EndStream
+        } else if packet.last_in_page() || index == 0 || index == 2 {
+            // the identification header sits alone on the first page and
+            // audio starts on a fresh page after the setup header
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        let absgp = if index < 3 { 0 } else { packet.absgp_page() };
+        let data = match index {
+            1 => comment_header(&packet.data, comments)?,
+            _ => packet.data,
+        };
+        writer
+            .write_packet(data.into_boxed_slice(), serial, end, absgp)
+            .map_err(|err| DownloadError::Ogg(err.to_string()))?;
+        index += 1;
+    }
+    if index < 3 {
+        return Err(DownloadError::Ogg(String::from("missing vorbis headers")));
+    }
+    Ok(writer.into_inner())
+}
+
+/// Builds a comment header keeping the vendor string of `original`.
+fn comment_header(original: &[u8], comments: &[String]) -> Result<Vec<u8>, DownloadError> {
+    if !original.starts_with(b"\x03vorbis") {
+        return Err(DownloadError::Ogg(String::from(
+            "the second packet is not a vorbis comment header",
+        )));
+    }
+    let vendor = original
+        .get(7..11)
+        .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+        .and_then(|len| original.get(11..11 + len))
+        .unwrap_or(VORBIS_VENDOR.as_bytes());
+
+    let mut header = b"\x03vorbis".to_vec();
+    header.extend((vendor.len() as u32).to_le_bytes());
+    header.extend(vendor);
+    header.extend((comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        header.extend((comment.len() as u32).to_le_bytes());
+        header.extend(comment.as_bytes());
+    }
+    header.push(1); // framing bit
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiooo::tests::sample_track;
+    use std::io::Cursor;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// A stream with the three vorbis headers and two audio packets.
+    fn sample_ogg() -> Vec<u8> {
+        let mut writer = PacketWriter::new(Vec::new());
+        let mut comments = b"\x03vorbis".to_vec();
+        comments.extend(4u32.to_le_bytes());
+        comments.extend(b"test");
+        comments.extend(0u32.to_le_bytes());
+        comments.push(1);
+        let packets: [(&[u8], PacketWriteEndInfo, u64); 5] = [
+            (b"\x01vorbis-id", PacketWriteEndInfo::EndPage, 0),
+            (&comments, PacketWriteEndInfo::NormalPacket, 0),
+            (b"\x05vorbis-setup", PacketWriteEndInfo::EndPage, 0),
+            (b"audio-1", PacketWriteEndInfo::EndPage, 1024),
+            (b"audio-2", PacketWriteEndInfo::EndStream, 2048),
+        ];
+        for (data, end, absgp) in packets {
+            writer.write_packet(data.into(), 42, end, absgp).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    fn read_comments(audio: &[u8]) -> (Vec<Vec<u8>>, Vec<String>) {
+        let mut reader = PacketReader::new(Cursor::new(audio));
+        let mut packets = Vec::new();
+        while let Some(packet) = reader.read_packet().unwrap() {
+            packets.push(packet.data);
+        }
+        let header = &packets[1];
+        let read_u32 =
+            |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap()) as usize;
+        let mut at = 11 + read_u32(7);
+        let count = read_u32(at);
+        at += 4;
+        let mut comments = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(at);
+            comments.push(String::from_utf8(header[at + 4..at + 4 + len].to_vec()).unwrap());
+            at += 4 + len;
+        }
+        (packets, comments)
+    }
+
+    #[test]
+    fn test_tag_ogg() {
+        let track = sample_track();
+        let comments = vorbis_comments(&track, Some(b"\x89PNG"));
+        let tagged = tag_ogg(Cursor::new(sample_ogg()), Vec::new(), &comments).unwrap();
+        let (packets, comments) = read_comments(&tagged);
+
+        assert_eq!(packets.len(), 5);
+        assert_eq!(packets[4], b"audio-2");
+        assert!(packets[1][11..15].eq(b"test"), "the vendor is kept");
+        assert!(comments.contains(&format!("TITLE={}", track.title)));
+        assert!(comments.contains(&String::from("COUNTRY=ARG")));
+        assert!(comments.contains(&String::from("LABEL=MH")));
+        // the songwriter is empty
+        assert!(!comments.iter().any(|c| c.starts_with("COMPOSER=")));
+        assert!(comments
+            .iter()
+            .any(|c| c.starts_with("METADATA_BLOCK_PICTURE=")));
+
+        assert!(matches!(
+            tag_ogg(Cursor::new(b"not an ogg"), Vec::new(), &comments),
+            Err(DownloadError::Ogg(_))
+        ));
+    }
+
+    #[test]
+    fn test_long_names_fit_the_file_system() {
+        let client = RadioooClient::builder().build().unwrap();
+        let downloader = Downloader::new("/music", client);
+        let mut track = sample_track();
+        // three bytes per character
+        track.title = "音".repeat(200);
+        let name = downloader.path(&track).file_name().unwrap().len();
+        assert!(name <= 255, "{name} bytes");
+        let part = downloader.path(&track).with_extension("part");
+        assert!(part.file_name().unwrap().len() <= 255);
+    }
+
+    #[tokio::test]
+    async fn test_download_mp3() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/track.mp3"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0xffu8; 64]))
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .cover_base_url(server.uri())
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(dir.path(), client);
+
+        let mut track = sample_track();
+        track.artist = String::from("AC/DC");
+        track.links.mpeg = format!("{}/track.mp3", server.uri());
+        // the cover is not served, the track is saved without it
+        let path = downloader.download(&track).await.unwrap();
+
+        assert_eq!(
+            path,
+            dir.path().join(format!("AC_DC - {}.mp3", track.title))
+        );
+        let tag = id3::Tag::read_from_path(&path).unwrap();
+        assert_eq!(tag.title(), Some(track.title.as_str()));
+        assert_eq!(tag.artist(), Some("AC/DC"));
+        assert_eq!(tag.year(), Some(1975));
+        assert_eq!(tag.get("TPUB").and_then(|f| f.content().text()), Some("MH"));
+        assert_eq!(tag.pictures().count(), 0);
+        assert!(!path.with_extension("part").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_ogg_slower_than_the_api_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/track.ogg"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(sample_ogg())
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let downloader = Downloader::new(dir.path(), client).format(AudioFormat::Ogg);
+
+        let mut track = sample_track();
+        track.cover = None;
+        track.image = None;
+        track.links.mpeg = format!("{}/track.mp3", server.uri());
+        track.links.ogg = format!("{}/track.ogg", server.uri());
+        let path = downloader.download(&track).await.unwrap();
+
+        let (packets, comments) = read_comments(&fs::read(&path).unwrap());
+        assert_eq!(packets.len(), 5);
+        assert!(comments.contains(&format!("TITLE={}", track.title)));
+        assert!(!path.with_extension("part").exists());
+        assert!(!path.with_extension("download").exists());
+    }
+}
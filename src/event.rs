@@ -4,10 +4,10 @@ use crossterm::event::{Event as CrosstermEvent, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
 use tokio::sync::mpsc;
 // use libmpv2::Mpv;
-use crate::app::AppResult;
+use crate::app::{AppResult, Response};
 
 /// Terminal events.
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug)]
 pub enum Event {
     /// Terminal tick.
     Tick,
@@ -17,6 +17,32 @@ pub enum Event {
     Mouse(MouseEvent),
     /// Terminal resize.
     Resize(u16, u16),
+    /// The player reached the end of the current track.
+    TrackEnded,
+    /// The player gave up on the current track, e.g. its stream was refused.
+    TrackFailed,
+    /// Command from outside the terminal, e.g. a media key through MPRIS.
+    Control(Control),
+    /// A request to the API finished.
+    Response(Box<Response>),
+}
+
+/// Playback command received from another program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Volume in percent.
+    SetVolume(u16),
+    /// Relative seek, in seconds.
+    Seek(f64),
+    /// Absolute seek, in seconds from the start of the track.
+    SetPosition(f64),
+    Quit,
 }
 
 /// Terminal event handler.
@@ -74,7 +100,7 @@ impl EventHandler {
                 };
             }
         });
-        
+
         Self {
             sender,
             receiver,
@@ -82,6 +108,11 @@ impl EventHandler {
         }
     }
 
+    /// Returns a sender to push events from outside the terminal, e.g. the player.
+    pub fn sender(&self) -> mpsc::UnboundedSender<Event> {
+        self.sender.clone()
+    }
+
     /// Receive the next event from the handler thread.
     ///
     /// This function will always block the current thread if
@@ -90,9 +121,6 @@ impl EventHandler {
         self.receiver
             .recv()
             .await
-            .ok_or(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "This is an IO error",
-            )))
+            .ok_or(Box::new(std::io::Error::other("This is an IO error")))
     }
 }
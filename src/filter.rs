@@ -0,0 +1,104 @@
+use crate::app::SelectedList;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::widgets::ListState;
+
+/// Narrows one of the lists to the entries matching a query, best first.
+///
+/// [`Filter::state`] indexes the filtered view, the state of the list itself
+/// only moves once a match is picked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    /// The filtered list.
+    pub list: SelectedList,
+    pub query: String,
+    /// Indexes of the matching entries in the whole list.
+    pub matches: Vec<usize>,
+    pub state: ListState,
+}
+
+impl Filter {
+    /// Starts filtering `list`, call [`Filter::update`] to fill the matches.
+    pub fn new(list: SelectedList) -> Self {
+        Self {
+            list,
+            query: String::new(),
+            matches: Vec::new(),
+            state: ListState::default(),
+        }
+    }
+
+    /// Ranks `entries`, each one matched on any of its keys, e.g. the code
+    /// and the name of a country.
+    pub fn update<K: AsRef<str>>(&mut self, entries: &[Vec<K>]) {
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, usize)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, keys)| {
+                let score = keys
+                    .iter()
+                    .filter_map(|key| matcher.fuzzy_match(key.as_ref(), &self.query))
+                    .max()?;
+                Some((score, index))
+            })
+            .collect();
+        // stable, so ties and the empty query keep the order of the list
+        scored.sort_by_key(|(score, _)| -score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.state.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    pub fn select_next(&mut self) {
+        let last = self.matches.len().checked_sub(1);
+        let next = self.state.selected().map_or(0, |i| i + 1);
+        self.state.select(last.map(|last| next.min(last)));
+    }
+
+    pub fn select_previous(&mut self) {
+        let previous = self.state.selected().map_or(0, |i| i.saturating_sub(1));
+        self.state
+            .select((!self.matches.is_empty()).then_some(previous));
+    }
+
+    /// Index in the whole list of the match under the cursor.
+    pub fn selected(&self) -> Option<usize> {
+        self.matches.get(self.state.selected()?).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let entries = [
+            vec!["ARG", "AR", "Argentina"],
+            vec!["FRA", "FR", "France"],
+            vec!["GAB", "GA", "Gabon"],
+        ];
+        let mut filter = Filter::new(SelectedList::Country);
+        filter.update(&entries);
+        assert_eq!(filter.matches, [0, 1, 2]);
+
+        filter.select_next();
+        filter.select_next();
+        filter.select_next();
+        assert_eq!(filter.selected(), Some(2));
+
+        filter.query.push_str("fr");
+        filter.update(&entries);
+        assert_eq!(filter.selected(), Some(1));
+
+        // matched on the code as well as on the name
+        filter.query = String::from("gab");
+        filter.update(&entries);
+        assert_eq!(filter.matches, [2]);
+
+        filter.query = String::from("zz");
+        filter.update(&entries);
+        assert!(filter.matches.is_empty());
+        assert_eq!(filter.selected(), None);
+    }
+}
@@ -1,92 +1,416 @@
-use std::borrow::Borrow;
-
-use crate::app::{App, AppResult, PlayState, SelectedList};
-use crate::radiooo::{self, Track};
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use log::{debug, info};
+use crate::app::{App, AppResult, SelectedList, View, SEEK_STEP};
+use crate::keymap::Action;
+use crate::radiooo::{Decade, Mood};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use log::debug;
+use ratatui::layout::{Margin, Position, Rect};
+use ratatui::widgets::ListState;
 
 /// Handles the key events and updates the state of [`App`].
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    if app.filter.is_some() {
+        handle_filter_key(key_event, app);
+        return Ok(());
+    }
+    let Some(action) = app.keymap.action(key_event) else {
+        return Ok(());
+    };
+    if app.show_help {
+        handle_help_action(action, app);
+        return Ok(());
+    }
+    match app.view {
+        View::Browse => handle_browse_action(action, app),
+        View::Favorites => handle_favorites_action(action, app),
+    }
+    Ok(())
+}
+
+/// Actions while the help popup is shown, the view underneath is left alone.
+fn handle_help_action(action: Action, app: &mut App) {
+    match action {
+        Action::Quit => app.quit(),
+        Action::Help | Action::Back => app.show_help = false,
+        _ => {}
+    }
+}
+
+/// Keys typed while a list is filtered, characters go to the query.
+///
+/// Keys that cannot be typed, e.g. `ctrl-c`, keep the actions they are bound
+/// to whatever the view.
+fn handle_filter_key(key_event: KeyEvent, app: &mut App) {
+    let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+    let Some(filter) = &mut app.filter else {
+        return;
+    };
     match key_event.code {
-        // Exit application on `ESC` or `q`
-        KeyCode::Esc | KeyCode::Char('q') => {
-            app.quit();
+        KeyCode::Esc => app.filter = None,
+        KeyCode::Enter => app.apply_filter(),
+        KeyCode::Backspace => app.filter_pop(),
+        KeyCode::Down => filter.select_next(),
+        KeyCode::Char('n') if ctrl => filter.select_next(),
+        KeyCode::Up => filter.select_previous(),
+        KeyCode::Char('p') if ctrl => filter.select_previous(),
+        KeyCode::Char(c) if !ctrl => app.filter_push(c),
+        _ => {
+            if let Some(action) = app.keymap.action(key_event) {
+                handle_player_action(action, app);
+            }
+        }
+    }
+}
+
+/// Handles the mouse events, hit-tested against the areas of the last frame.
+pub fn handle_mouse_events(mouse_event: MouseEvent, app: &mut App) -> AppResult<()> {
+    // the help popup covers the areas
+    if app.show_help {
+        if let MouseEventKind::Down(_) = mouse_event.kind {
+            app.show_help = false;
         }
-        // Exit application on `Ctrl-C`
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.quit();
+        return Ok(());
+    }
+    let position = Position::new(mouse_event.column, mouse_event.row);
+    let list = app.areas.list_at(position);
+    let on_volume = app.areas.volume.contains(position);
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if app.areas.play_state.contains(position) {
+                app.playpause();
+            } else if on_volume {
+                app.toggle_mute();
+            } else if let Some((list, area)) = list {
+                click_list(app, list, area, position);
             }
         }
+        MouseEventKind::ScrollUp if on_volume => app.increment_volume(),
+        MouseEventKind::ScrollDown if on_volume => app.decrement_volume(),
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+            let Some((list, _)) = list else {
+                return Ok(());
+            };
+            let up = mouse_event.kind == MouseEventKind::ScrollUp;
+            match &mut app.filter {
+                Some(filter) if filter.list == list && up => filter.select_previous(),
+                Some(filter) if filter.list == list => filter.select_next(),
+                _ if up => list_state(app, list).select_previous(),
+                _ => list_state(app, list).select_next(),
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-        // play pause
-        KeyCode::Char(' ') => app.playpause(),
+/// Focuses `list` and selects the entry under `position`, a filter of
+/// another list is cancelled.
+fn click_list(app: &mut App, list: SelectedList, area: Rect, position: Position) {
+    let inner = area.inner(Margin::new(1, 1));
+    if app
+        .filter
+        .as_ref()
+        .is_some_and(|filter| filter.list != list)
+    {
+        app.filter = None;
+    }
+    app.list_selected = list;
+    if !inner.contains(position) {
+        return;
+    }
+    let row = usize::from(position.y - inner.y);
+    let len = match &app.filter {
+        Some(filter) => filter.matches.len(),
+        None => match list {
+            SelectedList::Mood => Mood::ALL.len(),
+            SelectedList::Decade => Decade::ALL.len(),
+            SelectedList::Country => app.get_countries_available().len(),
+        },
+    };
+    let state = match &mut app.filter {
+        Some(filter) => &mut filter.state,
+        None => list_state(app, list),
+    };
+    let index = state.offset() + row;
+    if index < len {
+        state.select(Some(index));
+    }
+}
+
+fn list_state(app: &mut App, list: SelectedList) -> &mut ListState {
+    match list {
+        SelectedList::Mood => &mut app.mood_state,
+        SelectedList::Decade => &mut app.decade_state,
+        SelectedList::Country => &mut app.country_state,
+    }
+}
+
+/// Actions on the moods, decades and countries lists.
+fn handle_browse_action(action: Action, app: &mut App) {
+    match action {
+        Action::Back => app.quit(),
 
         // navigate ui
-        KeyCode::Down | KeyCode::Char('j') => match app.list_selected {
+        Action::Down => match app.list_selected {
             SelectedList::Mood => app.mood_state.select_next(),
             SelectedList::Decade => app.decade_state.select_next(),
             SelectedList::Country => app.country_state.select_next(),
         },
-        KeyCode::Up | KeyCode::Char('k') => match app.list_selected {
+        Action::Up => match app.list_selected {
             SelectedList::Mood => app.mood_state.select_previous(),
             SelectedList::Decade => app.decade_state.select_previous(),
             SelectedList::Country => app.country_state.select_previous(),
         },
-        KeyCode::Left | KeyCode::Char('h') => match app.list_selected {
+        Action::PreviousList => match app.list_selected {
             SelectedList::Country => app.list_selected = SelectedList::Decade,
             SelectedList::Decade => app.list_selected = SelectedList::Mood,
             SelectedList::Mood => app.list_selected = SelectedList::Country,
         },
-        KeyCode::Right | KeyCode::Char('l') => match app.list_selected {
+        Action::NextList => match app.list_selected {
             SelectedList::Country => app.list_selected = SelectedList::Mood,
             SelectedList::Decade => app.list_selected = SelectedList::Country,
             SelectedList::Mood => app.list_selected = SelectedList::Decade,
         },
+
+        // play mode
+        Action::CycleMode => app.cycle_mode(),
+
+        // filter
+        Action::Filter => app.start_filter(),
+
+        // multi selection
+        Action::ToggleMark => app.toggle_mark(),
+        Action::ClearMarks => app.clear_marks(),
+        Action::Select => match app.selection() {
+            Some(selection) => {
+                debug!("selection: {:?}", selection);
+                app.tune(selection);
+            }
+            None => app.current_setting = String::from("no country available for current setting"),
+        },
+        _ => handle_player_action(action, app),
+    }
+}
+
+/// Actions on the favorites list.
+fn handle_favorites_action(action: Action, app: &mut App) {
+    match action {
+        Action::Back => app.toggle_favorites_view(),
+        Action::Down => app.favorites_state.select_next(),
+        Action::Up => app.favorites_state.select_previous(),
+        Action::Select => app.play_favorite(),
+        Action::Save => app.save_favorite(),
+        _ => handle_player_action(action, app),
+    }
+}
+
+/// Actions available whatever the view.
+fn handle_player_action(action: Action, app: &mut App) {
+    match action {
+        Action::Quit => app.quit(),
+
+        // play pause
+        Action::PlayPause => app.playpause(),
+
         // VOLUME
-        KeyCode::Char('+') | KeyCode::Char('*') => {
-            app.increment_volume();
-        }
-        KeyCode::Char('-') | KeyCode::Char('/') => {
-            app.decrement_volume();
-        }
-        KeyCode::Char('m') => {
-            app.toggle_mute();
+        Action::VolumeUp => app.increment_volume(),
+        Action::VolumeDown => app.decrement_volume(),
+        Action::ToggleMute => app.toggle_mute(),
+
+        // seek
+        Action::SeekBackward => app.seek(-SEEK_STEP),
+        Action::SeekForward => app.seek(SEEK_STEP),
+
+        // next / previous
+        Action::NextTrack => app.next_track(),
+        Action::PreviousTrack => app.previous_track(),
+
+        // favorites
+        Action::ToggleFavorite => app.toggle_favorite(),
+        Action::Favorites => app.toggle_favorites_view(),
+
+        // offline copy
+        Action::Save => app.save_track(),
+
+        Action::Help => app.show_help = true,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::PlayState;
+    use crate::player::FakePlayer;
+    use crate::radiooo::{tests::sample_track, RadioooClient};
+    use crate::ui;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::time::Duration;
+
+    fn press(app: &mut App, code: KeyCode) {
+        handle_key_events(KeyEvent::new(code, KeyModifiers::NONE), app).unwrap();
+    }
+
+    fn app_with_fake_player() -> (App, FakePlayer) {
+        let player = FakePlayer::new();
+        let client = RadioooClient::builder().build().unwrap();
+        (App::new(Box::new(player.clone()), client), player)
+    }
+
+    #[test]
+    fn test_controls_reach_the_player() {
+        let (mut app, player) = app_with_fake_player();
+        assert_eq!(player.state().volume, 50);
+
+        app.play(sample_track());
+        assert_eq!(
+            player.state().loaded.as_deref(),
+            Some(sample_track().links.mpeg.as_str())
+        );
+
+        press(&mut app, KeyCode::Char(' '));
+        assert!(player.state().paused);
+        assert!(matches!(app.play_state, PlayState::Paused(_)));
+        press(&mut app, KeyCode::Char(' '));
+        assert!(!player.state().paused);
+        assert!(matches!(app.play_state, PlayState::Playing(_)));
+
+        press(&mut app, KeyCode::Char('+'));
+        assert_eq!(player.state().volume, 55);
+        press(&mut app, KeyCode::Char('-'));
+        press(&mut app, KeyCode::Char('-'));
+        assert_eq!(player.state().volume, 45);
+
+        press(&mut app, KeyCode::Char('m'));
+        assert!(player.state().muted && app.muted);
+        press(&mut app, KeyCode::Char('m'));
+        assert!(!player.state().muted && !app.muted);
+
+        // the help popup swallows the other keys
+        press(&mut app, KeyCode::Char('?'));
+        assert!(app.show_help);
+        press(&mut app, KeyCode::Char('m'));
+        assert!(!app.muted);
+        press(&mut app, KeyCode::Esc);
+        assert!(!app.show_help && app.running);
+    }
+
+    #[test]
+    fn test_mouse() {
+        let (mut app, player) = app_with_fake_player();
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        let mut draw = |app: &mut App| {
+            terminal.draw(|frame| ui::render(app, frame)).unwrap();
+        };
+        let mouse = |app: &mut App, kind, column, row| {
+            let event = MouseEvent {
+                kind,
+                column,
+                row,
+                modifiers: KeyModifiers::NONE,
+            };
+            handle_mouse_events(event, app).unwrap();
+        };
+        let click = MouseEventKind::Down(MouseButton::Left);
+
+        // the second entry of the moods list, the first row is the border
+        draw(&mut app);
+        mouse(&mut app, click, 5, 4);
+        assert_eq!(app.list_selected, SelectedList::Mood);
+        assert_eq!(app.mood_state.selected(), Some(1));
+        // below the last entry
+        mouse(&mut app, click, 5, 10);
+        assert_eq!(app.mood_state.selected(), Some(1));
+
+        mouse(&mut app, MouseEventKind::ScrollDown, 45, 5);
+        assert_eq!(app.list_selected, SelectedList::Mood);
+        assert_eq!(app.decade_state.selected(), Some(1));
+
+        mouse(&mut app, MouseEventKind::ScrollUp, 115, 0);
+        assert_eq!(player.state().volume, 55);
+        mouse(&mut app, click, 115, 0);
+        assert!(app.muted);
+
+        app.play(sample_track());
+        draw(&mut app);
+        mouse(&mut app, click, 2, 0);
+        assert!(player.state().paused);
+    }
+
+    #[test]
+    fn test_seek() {
+        let (mut app, player) = app_with_fake_player();
+        app.play(sample_track());
+        player.set_duration(Duration::from_secs(199));
+        app.tick();
+        assert_eq!(app.position, Some(Duration::ZERO));
+        assert_eq!(app.duration, Some(Duration::from_secs(199)));
+
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.position, Some(Duration::from_secs(10)));
+        assert_eq!(player.state().position, Some(Duration::from_secs(10)));
+        press(&mut app, KeyCode::Char(','));
+        press(&mut app, KeyCode::Char(','));
+        assert_eq!(app.position, Some(Duration::ZERO));
+        app.tick();
+        assert_eq!(app.position, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_remapped_keys() {
+        let (mut app, player) = app_with_fake_player();
+        app.keymap = toml::from_str("play_pause = \"p\"\nprevious_track = \"b\"").unwrap();
+        app.play(sample_track());
+
+        press(&mut app, KeyCode::Char(' '));
+        assert!(!player.state().paused);
+        press(&mut app, KeyCode::Char('p'));
+        assert!(player.state().paused);
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        handle_key_events(ctrl_c, &mut app).unwrap();
+        assert!(!app.running);
+    }
+
+    #[test]
+    fn test_filter_countries() {
+        let (mut app, _) = app_with_fake_player();
+        let countries: Vec<_> = ["ARG", "FRA", "GAB", "ITA"]
+            .map(|code| code.parse().unwrap())
+            .to_vec();
+        let available = crate::radiooo::CountryForDecade {
+            slow: countries.clone(),
+            fast: countries.clone(),
+            weird: countries,
+        };
+        app.country_availables
+            .insert(app.selected_decade(), available);
+        app.list_selected = SelectedList::Country;
+
+        press(&mut app, KeyCode::Char('/'));
+        for c in "fr".chars() {
+            press(&mut app, KeyCode::Char(c));
         }
-        KeyCode::Enter => {
-            let indexmoode = app.mood_state.selected().unwrap_or(0);
-            let indexdecade = app.decade_state.selected().unwrap_or(0);
-            let indexcountry = app.country_state.selected().unwrap_or(0);
-            debug!("indexes: {} {} {}", indexmoode, indexdecade, indexcountry);
-            let mood = radiooo::MOODS.get(indexmoode).unwrap_or(&"");
-            let decade = radiooo::DECADES.get(indexdecade).unwrap();
-            let country = app
-                .get_countries_available()
-                .get(indexcountry)
-                .unwrap()
-                .clone();
-            let opt = radiooo::get_track(mood, *decade, country.as_str());
-            if let Some(track) = opt {
-                info!("{:?}", track);
-                app.current_setting = format!(
-                    "{} - {} - {}",
-                    track.title,
-                    track.artist,
-                    track.album.unwrap_or_default()
-                );
-                app.mpv
-                    .command("loadfile", &[track.links.mpeg.as_str(), "replace"])
-                    .unwrap();
-                app.play_state = PlayState::Playing(track.clone());
-            } else {
-                app.current_setting = format!("{}", "no track was found for current setting")
-            }
+        assert_eq!(app.filter.as_ref().unwrap().matches, [1]);
+        press(&mut app, KeyCode::Enter);
+        assert!(app.filter.is_none());
+        assert_eq!(app.selected_country(), Some("FRA".parse().unwrap()));
+
+        // typed keys are not actions, escape leaves the cursor alone
+        press(&mut app, KeyCode::Char('/'));
+        for c in "qita".chars() {
+            press(&mut app, KeyCode::Char(c));
         }
+        assert!(app.running);
+        press(&mut app, KeyCode::Esc);
+        assert!(app.filter.is_none());
+        assert_eq!(app.country_state.selected(), Some(1));
 
-        // next
-        KeyCode::Char('n') => todo!("next"),
-        KeyCode::Char('p') => todo!("p"),
-        _ => {}
+        // erasing the whole query stops filtering
+        press(&mut app, KeyCode::Char('/'));
+        press(&mut app, KeyCode::Char('g'));
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.filter.as_ref().unwrap().matches, [0, 1, 2, 3]);
+        press(&mut app, KeyCode::Backspace);
+        assert!(app.filter.is_none());
     }
-    Ok(())
 }
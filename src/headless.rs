@@ -0,0 +1,134 @@
+use crate::app::{App, AppResult};
+use crate::daemon::PROGRESS_INTERVAL;
+use crate::event::Event;
+use crate::radiooo::{ExploreRequest, Track};
+use log::error;
+use std::io::Write;
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Failures in a row after which the station is given up.
+pub const MAX_FAILURES: u32 = 3;
+
+/// Wait before moving on after a failure, doubled after each one in a row.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Plays `station` until interrupted, writing a line to `out` whenever a
+/// track starts.
+///
+/// `events` only needs to carry [`Event::TrackEnded`] and
+/// [`Event::TrackFailed`], there is no terminal to read keys from. A track
+/// that cannot be fetched or played is skipped, the error is only returned
+/// after [`MAX_FAILURES`] in a row.
+pub async fn play(
+    app: &mut App,
+    station: ExploreRequest,
+    events: &mut UnboundedReceiver<Event>,
+    out: &mut impl Write,
+) -> AppResult<()> {
+    app.tune(station);
+    let mut progress = tokio::time::interval(PROGRESS_INTERVAL);
+    let mut failures = 0;
+    loop {
+        let failure = match app.error.take() {
+            Some(err) => Some(err),
+            None => {
+                if let Some(track) = app.play_state.track() {
+                    writeln!(out, "{}", now_playing(track))?;
+                    out.flush()?;
+                }
+                loop {
+                    tokio::select! {
+                        _ = progress.tick() => app.tick(),
+                        _ = signal::ctrl_c() => return Ok(()),
+                        event = events.recv() => match event {
+                            Some(Event::TrackEnded) => break None,
+                            Some(Event::TrackFailed) => {
+                                let track = app.play_state.track().map(now_playing);
+                                break Some(format!("failed to play {}", track.unwrap_or_default()));
+                            }
+                            Some(_) => {}
+                            None => return Ok(()),
+                        },
+                    }
+                }
+            }
+        };
+        match failure {
+            Some(err) => {
+                failures += 1;
+                error!("failure {} of {} in a row: {}", failures, MAX_FAILURES, err);
+                if failures >= MAX_FAILURES {
+                    return Err(err.into());
+                }
+                eprintln!("{err}, trying the next track");
+                tokio::select! {
+                    _ = tokio::time::sleep(RETRY_DELAY * 2u32.pow(failures - 1)) => {}
+                    _ = signal::ctrl_c() => return Ok(()),
+                }
+            }
+            None => failures = 0,
+        }
+        app.next_track();
+    }
+}
+
+/// `Artist - Title (year, country, mood)`.
+pub fn now_playing(track: &Track) -> String {
+    format!(
+        "{} - {} ({}, {}, {})",
+        track.artist, track.title, track.year, track.country, track.mood
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::FakePlayer;
+    use crate::radiooo::tests::TRACK_JSON;
+    use crate::radiooo::{Mood, RadioooClient};
+    use tokio::sync::mpsc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_headless_play() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(TRACK_JSON, "application/json"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+        let player = FakePlayer::new();
+        let mut app = App::new(Box::new(player.clone()), client);
+        let station = ExploreRequest::new(
+            vec![Mood::Fast],
+            vec!["1970".parse().unwrap()],
+            vec!["ARG".parse().unwrap()],
+        );
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let mut out = Vec::new();
+
+        // the second track fails, the next ones are not answered
+        sender.send(Event::TrackEnded).unwrap();
+        sender.send(Event::TrackFailed).unwrap();
+        let result = play(&mut app, station, &mut events, &mut out).await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("no track was found"));
+        // retried until MAX_FAILURES in a row
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2 + MAX_FAILURES as usize - 1);
+        let line = "Caballo Vapor - Busco El Sol, No Sé Adonde Voy (1975, ARG, FAST)\n";
+        assert_eq!(String::from_utf8(out).unwrap(), line.repeat(2));
+        assert!(player.state().loaded.is_some());
+    }
+}
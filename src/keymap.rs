@@ -0,0 +1,478 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a key does, interpreted by the handler of the current view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    /// Leaves the favorites, quits from the lists.
+    Back,
+    Down,
+    Up,
+    PreviousList,
+    NextList,
+    CycleMode,
+    ToggleMark,
+    ClearMarks,
+    /// Narrows the focused list to the entries matching what is typed next.
+    Filter,
+    /// Tunes to the selection, or plays the favorite under the cursor.
+    Select,
+    PlayPause,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    SeekBackward,
+    SeekForward,
+    NextTrack,
+    PreviousTrack,
+    ToggleFavorite,
+    Favorites,
+    /// Saves the current track, or the favorite under the cursor.
+    Save,
+    /// Shows every action with its keys.
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::Quit,
+        Action::Back,
+        Action::Down,
+        Action::Up,
+        Action::PreviousList,
+        Action::NextList,
+        Action::CycleMode,
+        Action::ToggleMark,
+        Action::ClearMarks,
+        Action::Filter,
+        Action::Select,
+        Action::PlayPause,
+        Action::VolumeUp,
+        Action::VolumeDown,
+        Action::ToggleMute,
+        Action::SeekBackward,
+        Action::SeekForward,
+        Action::NextTrack,
+        Action::PreviousTrack,
+        Action::ToggleFavorite,
+        Action::Favorites,
+        Action::Save,
+        Action::Help,
+    ];
+
+    /// Short label shown in the help.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Back => "back",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::PreviousList => "previous list",
+            Action::NextList => "next list",
+            Action::CycleMode => "mode",
+            Action::ToggleMark => "mark",
+            Action::ClearMarks => "clear marks",
+            Action::Filter => "filter",
+            Action::Select => "play",
+            Action::PlayPause => "play/pause",
+            Action::VolumeUp => "volume up",
+            Action::VolumeDown => "volume down",
+            Action::ToggleMute => "mute",
+            Action::SeekBackward => "seek backward",
+            Action::SeekForward => "seek forward",
+            Action::NextTrack => "next",
+            Action::PreviousTrack => "previous",
+            Action::ToggleFavorite => "like",
+            Action::Favorites => "favorites",
+            Action::Save => "save",
+            Action::Help => "help",
+        }
+    }
+
+    /// Sentence explaining the action in the help popup.
+    pub fn details(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit radiooooo",
+            Action::Back => "Leave the favorites, quit from the lists",
+            Action::Down => "Move the cursor down",
+            Action::Up => "Move the cursor up",
+            Action::PreviousList => "Focus the list on the left",
+            Action::NextList => "Focus the list on the right",
+            Action::CycleMode => "Cycle through the play modes",
+            Action::ToggleMark => {
+                "Mark the entry under the cursor, tracks are drawn from every mark"
+            }
+            Action::ClearMarks => "Clear the marks of the focused list",
+            Action::Filter => "Narrow the focused list to what is typed next",
+            Action::Select => "Tune to the selection, or play the favorite under the cursor",
+            Action::PlayPause => "Pause or resume the playback",
+            Action::VolumeUp => "Raise the volume",
+            Action::VolumeDown => "Lower the volume",
+            Action::ToggleMute => "Mute or unmute",
+            Action::SeekBackward => "Seek backward in the track",
+            Action::SeekForward => "Seek forward in the track",
+            Action::NextTrack => "Play the next track of the station",
+            Action::PreviousTrack => "Play the previous track of the history",
+            Action::ToggleFavorite => "Like or unlike the current track",
+            Action::Favorites => "Show or hide the favorites",
+            Action::Save => "Save the current track, or the favorite under the cursor, offline",
+            Action::Help => "Show or hide this help",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "ctrl-c"],
+            Action::Back => &["esc"],
+            Action::Down => &["down", "j"],
+            Action::Up => &["up", "k"],
+            Action::PreviousList => &["left", "h"],
+            Action::NextList => &["right", "l"],
+            Action::CycleMode => &["o"],
+            Action::ToggleMark => &["x"],
+            Action::ClearMarks => &["X"],
+            Action::Filter => &["/"],
+            Action::Select => &["enter"],
+            Action::PlayPause => &["space"],
+            Action::VolumeUp => &["+", "*"],
+            Action::VolumeDown => &["-"],
+            Action::ToggleMute => &["m"],
+            Action::SeekBackward => &[","],
+            Action::SeekForward => &["."],
+            Action::NextTrack => &["n"],
+            Action::PreviousTrack => &["p"],
+            Action::ToggleFavorite => &["f"],
+            Action::Favorites => &["F"],
+            Action::Save => &["s"],
+            Action::Help => &["?"],
+        }
+    }
+}
+
+/// A key with its modifiers, written `ctrl-c`, `alt-enter`, `space`, `X`…
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Shift is folded into the case of a character: terminals send
+    /// `shift-x` as `X`, and `ctrl-c` as `c` whatever the case in the config.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Self {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::Char(c) if !modifiers.is_empty() => Self {
+                code: KeyCode::Char(c.to_ascii_lowercase()),
+                modifiers,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // `-` alone, or after a modifier, is the minus key
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier `{prefix}` in `{s}`")),
+            };
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key `{s}`")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `ctrl-X` would be read back as `ctrl-x`
+        let shifted = !self.modifiers.is_empty()
+            && matches!(self.code, KeyCode::Char(c) if c.is_ascii_uppercase());
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) || (shifted && modifier == KeyModifiers::SHIFT) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) if shifted => write!(f, "{}", c.to_ascii_lowercase()),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("backtab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Insert => f.write_str("insert"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBinding {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// One key or several for an action in the `[keys]` table.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(KeyBinding),
+    Many(Vec<KeyBinding>),
+}
+
+/// Resolves keys to actions.
+///
+/// The `[keys]` table of the configuration replaces the default keys of the
+/// actions it lists:
+///
+/// ```toml
+/// [keys]
+/// play_pause = "p"
+/// previous_track = ["b", "ctrl-p"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "HashMap<Action, Keys>")]
+pub struct Keymap {
+    keys: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|key| key.parse().expect("default keys are valid"))
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Self { keys }
+    }
+}
+
+impl TryFrom<HashMap<Action, Keys>> for Keymap {
+    type Error = String;
+
+    fn try_from(overrides: HashMap<Action, Keys>) -> Result<Self, Self::Error> {
+        let mut keymap = Self::default();
+        for (action, keys) in overrides {
+            let keys = match keys {
+                Keys::One(key) => vec![key],
+                Keys::Many(keys) => keys,
+            };
+            keymap.keys.insert(action, keys);
+        }
+        keymap.validate()?;
+        Ok(keymap)
+    }
+}
+
+impl Keymap {
+    /// Action bound to `event`, if any.
+    pub fn action(&self, event: KeyEvent) -> Option<Action> {
+        let binding = KeyBinding::from(event);
+        Action::ALL
+            .into_iter()
+            .find(|action| self.keys(*action).contains(&binding))
+    }
+
+    /// Keys bound to `action`, the first one being the one shown in the help.
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// `key label` pairs of `actions`, skipping the unbound ones.
+    pub fn help(&self, actions: &[Action]) -> Vec<(String, &'static str)> {
+        actions
+            .iter()
+            .filter_map(|action| {
+                let key = self.keys(*action).first()?;
+                Some((key.to_string(), action.description()))
+            })
+            .collect()
+    }
+
+    /// Rejects a key bound to two actions, and a keymap without a way to quit.
+    fn validate(&self) -> Result<(), String> {
+        let mut bound: HashMap<KeyBinding, Action> = HashMap::new();
+        for action in Action::ALL {
+            for key in self.keys(action) {
+                if let Some(other) = bound.insert(*key, action) {
+                    return Err(format!(
+                        "`{key}` is bound to both {} and {}",
+                        other.description(),
+                        action.description()
+                    ));
+                }
+            }
+        }
+        if self.keys(Action::Quit).is_empty() {
+            return Err(String::from("no key is bound to quit"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bindings() {
+        for key in [
+            "q",
+            "X",
+            "space",
+            "ctrl-c",
+            "alt-shift-x",
+            "ctrl-shift-x",
+            "alt-enter",
+            "-",
+            "ctrl--",
+            "f5",
+            "pageup",
+        ] {
+            let binding: KeyBinding = key.parse().unwrap();
+            assert_eq!(binding.to_string(), key);
+        }
+        // as crossterm reports the keys
+        let event = |code, modifiers| KeyBinding::from(KeyEvent::new(code, modifiers));
+        for (key, code, modifiers) in [
+            ("Ctrl-C", KeyCode::Char('c'), KeyModifiers::CONTROL),
+            ("shift-x", KeyCode::Char('X'), KeyModifiers::SHIFT),
+            ("X", KeyCode::Char('X'), KeyModifiers::SHIFT),
+            (
+                "alt-shift-x",
+                KeyCode::Char('X'),
+                KeyModifiers::ALT | KeyModifiers::SHIFT,
+            ),
+            (
+                "ctrl-shift-x",
+                KeyCode::Char('X'),
+                KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            ),
+            ("shift-tab", KeyCode::Tab, KeyModifiers::SHIFT),
+        ] {
+            assert_eq!(key.parse::<KeyBinding>().unwrap(), event(code, modifiers));
+        }
+        assert_ne!(
+            "ctrl-c".parse::<KeyBinding>().unwrap(),
+            event(KeyCode::Char('c'), KeyModifiers::NONE)
+        );
+        assert!("hyper-x".parse::<KeyBinding>().is_err());
+        assert!("f13".parse::<KeyBinding>().is_err());
+        assert!("nope".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn test_keymap() {
+        let keymap = Keymap::default();
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('X'), KeyModifiers::SHIFT)),
+            Some(Action::ClearMarks)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('c'), KeyModifiers::NONE)),
+            None
+        );
+
+        let keymap: Keymap =
+            toml::from_str("play_pause = \"p\"\nprevious_track = [\"b\"]").unwrap();
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('p'), KeyModifiers::NONE)),
+            Some(Action::PlayPause)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char(' '), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.help(&[Action::PlayPause, Action::PreviousTrack]),
+            [
+                (String::from("p"), "play/pause"),
+                (String::from("b"), "previous")
+            ]
+        );
+
+        let error = toml::from_str::<Keymap>("play_pause = \"q\"").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`q` is bound to both quit and play/pause"));
+        assert!(toml::from_str::<Keymap>("quit = []").is_err());
+        assert!(toml::from_str::<Keymap>("dance = \"d\"").is_err());
+    }
+}
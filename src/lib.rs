@@ -13,5 +13,47 @@ pub mod tui;
 /// Event handler.
 pub mod handler;
 
+/// Key bindings.
+pub mod keymap;
+
+/// Fuzzy filter of the lists.
+pub mod filter;
+
 /// Radioooo stuff
 pub mod radiooo;
+
+/// Country names, flags and regions.
+pub mod country;
+
+/// Audio playback.
+pub mod player;
+
+/// Command line arguments.
+pub mod cli;
+
+/// Playback without the terminal interface.
+pub mod headless;
+
+/// Background player controlled through a socket.
+pub mod daemon;
+
+/// Media keys and desktop integration over D-Bus.
+pub mod mpris;
+
+/// Listens submitted to ListenBrainz.
+pub mod scrobble;
+
+/// Configuration file.
+pub mod config;
+
+/// Countries available for each decade, cached on disk.
+pub mod availability;
+
+/// Cover art.
+pub mod cover;
+
+/// Favorite tracks.
+pub mod library;
+
+/// Offline copies of the tracks.
+pub mod download;
@@ -0,0 +1,174 @@
+use crate::radiooo::Track;
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Error returned while reading or writing the library file.
+#[derive(Debug, Error)]
+pub enum LibraryError {
+    #[error("failed to access the library {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("the library {path} is corrupted: {source}")]
+    Corrupted {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// A liked track.
+///
+/// The stream links of [`Favorite::track`] expire, they must be requested
+/// again before replaying it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub track: Track,
+    /// Seconds since the epoch.
+    pub added_at: u64,
+}
+
+/// Favorite tracks, persisted as JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Library {
+    path: PathBuf,
+    pub favorites: Vec<Favorite>,
+}
+
+impl Library {
+    /// `$XDG_DATA_HOME/radiooooo/favorites.json`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("radiooooo").join("favorites.json"))
+    }
+
+    /// Opens the library stored at `path`, which may not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, LibraryError> {
+        let path = path.into();
+        let favorites = match fs::read(&path) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).map_err(|source| LibraryError::Corrupted {
+                    path: path.clone(),
+                    source,
+                })?
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(source) => return Err(LibraryError::Io { path, source }),
+        };
+        Ok(Self { path, favorites })
+    }
+
+    /// Opens the library stored at `path`, moving a corrupted file aside to
+    /// `favorites.json.corrupted` and starting over rather than failing.
+    pub fn open_or_recover(path: impl Into<PathBuf>) -> Result<Self, LibraryError> {
+        match Self::open(path) {
+            Err(LibraryError::Corrupted { path, source }) => {
+                let aside = path.with_extension("json.corrupted");
+                error!(
+                    "the library {} is corrupted, moved to {}: {}",
+                    path.display(),
+                    aside.display(),
+                    source
+                );
+                if let Err(source) = fs::rename(&path, &aside) {
+                    return Err(LibraryError::Io { path, source });
+                }
+                Ok(Self {
+                    path,
+                    favorites: Vec::new(),
+                })
+            }
+            result => result,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, track: &Track) -> bool {
+        self.favorites.iter().any(|f| f.track._id == track._id)
+    }
+
+    /// Likes `track`, or unlikes it if it was already, and saves the library.
+    ///
+    /// Returns whether the track is now a favorite.
+    pub fn toggle(&mut self, track: &Track) -> Result<bool, LibraryError> {
+        let liked = if self.contains(track) {
+            self.favorites.retain(|f| f.track._id != track._id);
+            false
+        } else {
+            let added_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            self.favorites.push(Favorite {
+                track: track.clone(),
+                added_at,
+            });
+            true
+        };
+        self.save()?;
+        Ok(liked)
+    }
+
+    /// Writes the library to its file, through a temporary file so that a
+    /// crash never leaves it half written.
+    pub fn save(&self) -> Result<(), LibraryError> {
+        let io_error = |source| LibraryError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let json = serde_json::to_vec_pretty(&self.favorites).expect("tracks are serializable");
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(io_error)?;
+        fs::rename(&tmp, &self.path).map_err(io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiooo::tests::sample_track;
+
+    #[test]
+    fn test_library_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("radiooooo").join("favorites.json");
+        let mut library = Library::open(&path).unwrap();
+        assert!(library.favorites.is_empty());
+
+        let track = sample_track();
+        assert!(library.toggle(&track).unwrap());
+        assert!(library.contains(&track));
+
+        let reopened = Library::open(&path).unwrap();
+        assert_eq!(reopened, library);
+
+        assert!(!library.toggle(&track).unwrap());
+        assert!(Library::open(&path).unwrap().favorites.is_empty());
+
+        fs::write(&path, "{").unwrap();
+        assert!(matches!(
+            Library::open(&path),
+            Err(LibraryError::Corrupted { .. })
+        ));
+
+        // kept aside rather than overwritten by the next save
+        let recovered = Library::open_or_recover(&path).unwrap();
+        assert!(recovered.favorites.is_empty());
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(path.with_extension("json.corrupted")).unwrap(),
+            "{"
+        );
+    }
+}
@@ -1,63 +1,213 @@
+use clap::Parser;
 use libmpv2::Mpv;
-use log::error;
-use log::info;
-use log::warn;
-use log::{debug, LevelFilter};
-use log4rs::append::console::ConsoleAppender;
+use log::{error, LevelFilter};
 use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Root};
-use log4rs::Config;
 use radiooooo::app::{App, AppResult};
+use radiooooo::availability::AvailabilityCache;
+use radiooooo::cli::{Cli, Command, CtlCommand, Selection};
+use radiooooo::config::Config;
+use radiooooo::cover::{CoverStore, GraphicsProtocol};
+use radiooooo::daemon::{Daemon, DaemonClient, Playback, Status};
+use radiooooo::download::{AudioFormat, Downloader};
 use radiooooo::event::{Event, EventHandler};
-use radiooooo::handler::handle_key_events;
+use radiooooo::handler::{handle_key_events, handle_mouse_events};
+use radiooooo::headless;
+use radiooooo::library::Library;
+use radiooooo::mpris::MprisServer;
+use radiooooo::player::spawn_mpv_event_listener;
+use radiooooo::radiooo::{ExploreRequest, Mood, PlayMode, RadioooClient};
+use radiooooo::scrobble::Scrobbler;
 use radiooooo::tui::Tui;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
+use serde_json::{json, Value};
 use std::io;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let cli = Cli::parse();
+    let config = match cli.config.clone().or_else(Config::default_path) {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+    let format = cli.format.unwrap_or(config.player.format);
+
     // logging
-    let logfile = FileAppender::builder().build("log/requests.log").unwrap();
-    let config = Config::builder()
+    let logfile = FileAppender::builder().build(&config.log_file)?;
+    let log_config = log4rs::Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(logfile)))
-        .build(Root::builder().appender("stdout").build(LevelFilter::Debug))
-        .unwrap();
-    let _handle = log4rs::init_config(config).unwrap();
+        .build(Root::builder().appender("stdout").build(LevelFilter::Debug))?;
+    let _handle = log4rs::init_config(log_config)?;
 
-    // Create an `Mpv` and set some properties.
-    let mpv = Mpv::with_initializer(|init| {
-        init.set_property("vo", "null")?;
-        Ok(())
-    })
-    .unwrap();
+    let client = RadioooClient::builder()
+        .base_url(&config.api.base_url)
+        .cover_base_url(&config.api.cover_base_url)
+        .build()?;
+    let downloader = cli
+        .music_dir
+        .clone()
+        .or_else(|| config.music_dir())
+        .or_else(Downloader::default_dir)
+        .map(|dir| Downloader::new(dir, client.clone()).format(format));
+    // the favorites are not worth refusing to start
+    let library = Library::default_path().and_then(|path| {
+        Library::open_or_recover(path)
+            .map_err(|err| error!("{}", err))
+            .ok()
+    });
+    let scrobbler = match &config.scrobble.token {
+        Some(token) => {
+            let scrobbler = Scrobbler::new(&config.scrobble.url, token)?;
+            match Scrobbler::default_queue_path() {
+                Some(path) => Some(scrobbler.queue_file(path)?),
+                None => Some(scrobbler),
+            }
+        }
+        None => None,
+    };
 
-    // Create an application.
-    let mut app = App::new(mpv);
+    let mode = cli.mode.unwrap_or(config.defaults.mode);
+    let socket = cli.socket.clone().unwrap_or_else(Daemon::default_path);
+    match cli.command {
+        Some(Command::Download { ids, favorites }) => {
+            let Some(downloader) = downloader else {
+                return Err("no music directory, pass --music-dir".into());
+            };
+            return download(&client, &downloader, library.as_ref(), ids, favorites).await;
+        }
+        Some(Command::Play(selection)) => {
+            let request = explore_request(selection, &config, mode, cli.island)?;
+            let mpv = new_mpv()?;
+            let (sender, mut events) = mpsc::unbounded_channel();
+            spawn_mpv_event_listener(&mpv, sender);
+            let mut app = App::new(Box::new(mpv), client);
+            configure_player(&mut app, &config, format);
+            app.scrobbler = scrobbler;
+            return headless::play(&mut app, request, &mut events, &mut io::stdout()).await;
+        }
+        Some(Command::Daemon) => {
+            let daemon = Daemon::bind(socket)?;
+            let mpv = new_mpv()?;
+            let (sender, mut events) = mpsc::unbounded_channel();
+            spawn_mpv_event_listener(&mpv, sender.clone());
+            let daemon = match MprisServer::start(sender.clone()).await {
+                Ok(server) => daemon.mpris(server),
+                Err(err) => {
+                    error!("failed to register on the session bus: {}", err);
+                    daemon
+                }
+            };
+            let mut app = App::new(Box::new(mpv), client);
+            configure_player(&mut app, &config, format);
+            // the requests to the API must not hold up the clients
+            app.events = Some(sender.clone());
+            app.scrobbler = scrobbler;
+            app.mode = mode;
+            app.island = cli.island;
+            eprintln!("listening on {}", daemon.path().display());
+            return Ok(daemon.run(&mut app, &mut events).await?);
+        }
+        Some(Command::Ctl { command }) => {
+            let daemon = DaemonClient::connect(&socket)?;
+            let status: Status = match command {
+                CtlCommand::Play => daemon.call("play", Value::Null)?,
+                CtlCommand::Pause => daemon.call("pause", Value::Null)?,
+                CtlCommand::Stop => daemon.call("stop", Value::Null)?,
+                CtlCommand::Next => daemon.call("next", Value::Null)?,
+                CtlCommand::Previous => daemon.call("previous", Value::Null)?,
+                CtlCommand::Volume { volume } => {
+                    daemon.call("set_volume", json!({ "volume": volume }))?
+                }
+                CtlCommand::Mute => daemon.call("set_muted", json!({ "muted": true }))?,
+                CtlCommand::Unmute => daemon.call("set_muted", json!({ "muted": false }))?,
+                CtlCommand::Tune(selection) => {
+                    let request = explore_request(selection, &config, mode, cli.island)?;
+                    daemon.call("set_selection", request)?
+                }
+                CtlCommand::Status => daemon.status()?,
+                CtlCommand::Quit => daemon.call("quit", Value::Null)?,
+            };
+            println!("{}", describe(&status));
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // Create an application, playing itself or through the daemon.
+    let events = EventHandler::new(config.ui.tick_rate);
+    let mut app = if cli.remote {
+        let mut app = App::remote(DaemonClient::connect(&socket)?, client.clone());
+        app.max_volume = config.player.max_volume;
+        app.volume_increment = config.player.volume_increment;
+        app
+    } else {
+        let mpv = new_mpv()?;
+        spawn_mpv_event_listener(&mpv, events.sender());
+        let mut app = App::new(Box::new(mpv), client.clone());
+        configure_player(&mut app, &config, format);
+        // the daemon scrobbles what it plays in remote mode
+        app.scrobbler = scrobbler;
+        app
+    };
+    app.keymap = config.keys.clone();
+    app.events = Some(events.sender());
+    if config.ui.covers {
+        app.covers = CoverStore::default_dir().map(|dir| CoverStore::new(dir, client));
+    }
+    app.graphics = config.ui.graphics.unwrap_or_else(GraphicsProtocol::detect);
+    app.library = library;
+    app.downloader = downloader;
+    app.mode = mode;
+    app.island = cli.island;
+    app.availability_cache = AvailabilityCache::default_path().map(|path| {
+        AvailabilityCache::new(path).ttl(Duration::from_secs(config.api.availability_ttl))
+    });
     app.populate_countries_available();
+    let defaults = &config.defaults;
+    app.select(defaults.mood, defaults.decade, defaults.country);
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
     let terminal = Terminal::new(backend)?;
-    let events = EventHandler::new(250);
+    // the daemon already publishes the player it owns
+    let mpris = if cli.remote {
+        None
+    } else {
+        MprisServer::start(events.sender())
+            .await
+            .map_err(|err| error!("failed to register on the session bus: {}", err))
+            .ok()
+    };
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
-    // mpv.command(
-    //     "loadfile",
-    //     &["https://www.youtube.com/watch?v=VLnWf1sQkjY", "append-play"],
-    // )
-    // .unwrap();
     // Start the main loop.
     while app.running {
         // Render the user interface.
         tui.draw(&mut app)?;
         // Handle events.
         match tui.events.next().await? {
-            Event::Tick => app.tick(),
+            Event::Tick => {
+                app.tick();
+                if let Some(mpris) = &mpris {
+                    if let Err(err) = mpris.update(&app).await {
+                        error!("failed to update MPRIS: {}", err);
+                    }
+                }
+            }
             Event::Key(key_event) => handle_key_events(key_event, &mut app)?,
-            Event::Mouse(_) => {}
+            Event::Mouse(mouse_event) => handle_mouse_events(mouse_event, &mut app)?,
             Event::Resize(_, _) => {}
+            Event::TrackEnded => app.next_track(),
+            Event::TrackFailed => {
+                app.track_failed();
+                app.next_track();
+            }
+            Event::Control(control) => app.control(control),
+            Event::Response(response) => app.handle_response(*response),
         }
     }
 
@@ -65,3 +215,107 @@ async fn main() -> AppResult<()> {
     tui.exit()?;
     Ok(())
 }
+
+/// Request for `selection`, the configured defaults filling the gaps. The
+/// island is only sent in the islands mode, as [`App::selection`] does.
+fn explore_request(
+    selection: Selection,
+    config: &Config,
+    mode: PlayMode,
+    island: Option<String>,
+) -> AppResult<ExploreRequest> {
+    let Selection {
+        moods,
+        decades,
+        countries,
+    } = selection;
+    let defaults = &config.defaults;
+    let moods = match (moods.is_empty(), defaults.mood) {
+        (false, _) => moods,
+        (true, Some(mood)) => vec![mood],
+        (true, None) => Mood::ALL.to_vec(),
+    };
+    let decades = match (decades.is_empty(), defaults.decade) {
+        (false, _) => decades,
+        (true, Some(decade)) => vec![decade],
+        (true, None) => return Err("pass at least one --decade".into()),
+    };
+    let countries = match (countries.is_empty(), defaults.country) {
+        (false, _) => countries,
+        (true, Some(country)) => vec![country],
+        (true, None) => return Err("pass at least one --country".into()),
+    };
+    let request = ExploreRequest::new(moods, decades, countries).mode(mode);
+    Ok(match (island, mode) {
+        (Some(island), PlayMode::Islands) => request.island(island),
+        _ => request,
+    })
+}
+
+/// One line summary of the daemon state.
+fn describe(status: &Status) -> String {
+    let volume = if status.muted {
+        String::from("muted")
+    } else {
+        format!("{}%", status.volume)
+    };
+    let playback = match status.playback {
+        Playback::Playing => "playing",
+        Playback::Paused => "paused",
+        Playback::Stopped => "stopped",
+    };
+    match &status.track {
+        Some(track) => format!("{playback}: {} [{volume}]", headless::now_playing(track)),
+        None => format!("{playback} [{volume}]"),
+    }
+}
+
+/// Creates an `Mpv` for audio only.
+fn new_mpv() -> AppResult<Mpv> {
+    let mpv = Mpv::with_initializer(|init| {
+        init.set_property("vo", "null")?;
+        Ok(())
+    })
+    .map_err(|err| format!("failed to start mpv: {:?}", err))?;
+    Ok(mpv)
+}
+
+/// Applies the `[player]` settings.
+fn configure_player(app: &mut App, config: &Config, format: AudioFormat) {
+    app.max_volume = config.player.max_volume;
+    app.volume_increment = config.player.volume_increment;
+    app.set_volume(config.player.volume);
+    app.format = format;
+}
+
+/// Saves the tracks `ids`, and every favorite if `favorites` is set.
+async fn download(
+    client: &RadioooClient,
+    downloader: &Downloader,
+    library: Option<&Library>,
+    ids: Vec<String>,
+    favorites: bool,
+) -> AppResult<()> {
+    let mut tracks = Vec::new();
+    for id in ids {
+        tracks.push(client.get_track_by_id(&id).await?);
+    }
+    if favorites {
+        let library = library.ok_or("no favorites library")?;
+        tracks.extend(library.favorites.iter().map(|f| f.track.clone()));
+    }
+    let mut failed = 0;
+    for track in &tracks {
+        match downloader.download(track).await {
+            Ok(path) => println!("{}", path.display()),
+            Err(err) => {
+                eprintln!("{} - {}: {}", track.artist, track.title, err);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} of {} tracks could not be saved", failed, tracks.len()).into()),
+    }
+}
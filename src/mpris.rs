@@ -0,0 +1,489 @@
+use crate::app::App;
+use crate::daemon::{Playback, Status};
+use crate::event::{Control, Event};
+use crate::radiooo::Track;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{connection, fdo, interface, Connection};
+
+/// Well-known name of the player on the session bus.
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.radiooooo";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// What the bus is told about the player, refreshed by [`MprisServer::update`].
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    status: Status,
+    art_url: Option<String>,
+    /// See [`App::seeks`].
+    seeks: u64,
+}
+
+type SharedSnapshot = Arc<Mutex<Option<Snapshot>>>;
+
+/// `org.mpris.MediaPlayer2`, the player as an application.
+struct Root {
+    events: UnboundedSender<Event>,
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        let _ = self.events.send(Event::Control(Control::Quit));
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "radiooooo"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// `org.mpris.MediaPlayer2.Player`, commands are forwarded to the event loop.
+struct Player {
+    events: UnboundedSender<Event>,
+    snapshot: SharedSnapshot,
+}
+
+impl Player {
+    fn send(&self, control: Control) {
+        let _ = self.events.send(Event::Control(control));
+    }
+
+    fn status<T>(&self, f: impl FnOnce(&Status) -> T) -> Option<T> {
+        self.snapshot.lock().unwrap().as_ref().map(|s| f(&s.status))
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.send(Control::Next);
+    }
+
+    fn previous(&self) {
+        self.send(Control::Previous);
+    }
+
+    fn pause(&self) {
+        self.send(Control::Pause);
+    }
+
+    fn play_pause(&self) {
+        self.send(Control::PlayPause);
+    }
+
+    fn stop(&self) {
+        self.send(Control::Stop);
+    }
+
+    fn play(&self) {
+        self.send(Control::Play);
+    }
+
+    /// `offset` is in microseconds.
+    fn seek(&self, offset: i64) {
+        self.send(Control::Seek(offset as f64 / 1_000_000.0));
+    }
+
+    /// `position` is in microseconds, ignored if `track_id` is not the
+    /// current track any more or if it is past the end.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        let current = self
+            .snapshot
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|s| Some((track_path(s.status.track.as_ref()?), length(&s.status)?)));
+        let Some((current_id, length)) = current else {
+            return;
+        };
+        let position = position as f64 / 1_000_000.0;
+        if track_id != current_id || !(0.0..=length).contains(&position) {
+            return;
+        }
+        self.send(Control::SetPosition(position));
+    }
+
+    /// The position jumped, `position` is in microseconds.
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    fn open_uri(&self, _uri: &str) -> fdo::Result<()> {
+        Err(fdo::Error::NotSupported(String::from(
+            "tracks are drawn from radiooooo",
+        )))
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        match self.status(|s| s.playback) {
+            Some(Playback::Playing) => "Playing",
+            Some(Playback::Paused) => "Paused",
+            _ => "Stopped",
+        }
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.snapshot
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(metadata)
+            .unwrap_or_default()
+    }
+
+    /// 1.0 is 100%.
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.status(|s| f64::from(s.volume) / 100.0)
+            .unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        let percent = (volume.max(0.0) * 100.0).round().min(f64::from(u16::MAX));
+        self.send(Control::SetVolume(percent as u16));
+    }
+
+    /// Microseconds.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.status(|s| s.position)
+            .flatten()
+            .map_or(0, |secs| (secs * 1_000_000.0) as i64)
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        true
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// `mpris:trackid` of `track`.
+fn track_path(track: &Track) -> ObjectPath<'static> {
+    ObjectPath::try_from(format!("/org/radiooooo/track/{}", track._id))
+        .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked("/org/radiooooo/track"))
+}
+
+/// Length of the current track in seconds.
+fn length(status: &Status) -> Option<f64> {
+    let track = status.track.as_ref()?;
+    Some(status.duration.unwrap_or(f64::from(track.length)))
+}
+
+/// `xesam:` and `mpris:` fields of the current track.
+fn metadata(snapshot: &Snapshot) -> HashMap<String, OwnedValue> {
+    let Some(track) = &snapshot.status.track else {
+        return HashMap::new();
+    };
+    let mut fields: Vec<(&str, Value)> = vec![
+        ("mpris:trackid", track_path(track).into()),
+        ("xesam:title", track.title.clone().into()),
+        ("xesam:artist", vec![track.artist.clone()].into()),
+    ];
+    let length = length(&snapshot.status).unwrap_or_default();
+    fields.push(("mpris:length", ((length * 1_000_000.0) as i64).into()));
+    if let Some(album) = track.album.as_ref().filter(|a| !a.is_empty()) {
+        fields.push(("xesam:album", album.clone().into()));
+    }
+    if let Some(songwriter) = track.songwriter.as_ref().filter(|s| !s.is_empty()) {
+        fields.push(("xesam:composer", vec![songwriter.clone()].into()));
+    }
+    if let Some(art_url) = &snapshot.art_url {
+        fields.push(("mpris:artUrl", art_url.clone().into()));
+    }
+    fields
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.try_to_owned().ok()?)))
+        .collect()
+}
+
+/// The player exposed on the session bus as [`BUS_NAME`].
+#[derive(Debug, Clone)]
+pub struct MprisServer {
+    connection: Connection,
+    snapshot: SharedSnapshot,
+}
+
+impl MprisServer {
+    /// Registers on the session bus, commands are sent to `events`.
+    pub async fn start(events: UnboundedSender<Event>) -> zbus::Result<Self> {
+        Self::serve(connection::Builder::session()?, events).await
+    }
+
+    /// Registers on the bus at `address`, e.g. a private `dbus-daemon`.
+    pub async fn start_at(address: &str, events: UnboundedSender<Event>) -> zbus::Result<Self> {
+        Self::serve(connection::Builder::address(address)?, events).await
+    }
+
+    async fn serve(
+        builder: connection::Builder<'_>,
+        events: UnboundedSender<Event>,
+    ) -> zbus::Result<Self> {
+        let snapshot = SharedSnapshot::default();
+        let connection = builder
+            .name(BUS_NAME)?
+            .serve_at(
+                OBJECT_PATH,
+                Root {
+                    events: events.clone(),
+                },
+            )?
+            .serve_at(
+                OBJECT_PATH,
+                Player {
+                    events,
+                    snapshot: snapshot.clone(),
+                },
+            )?
+            .build()
+            .await?;
+        Ok(Self {
+            connection,
+            snapshot,
+        })
+    }
+
+    /// Publishes the state of `app`, signaling the properties that changed.
+    ///
+    /// The state is read before the returned future is polled, which does not
+    /// borrow `app`.
+    pub fn update<'a>(&'a self, app: &App) -> impl Future<Output = zbus::Result<()>> + Send + 'a {
+        let art_url = app.play_state.track().and_then(|track| {
+            let image = track.cover.as_ref().or(track.image.as_ref())?;
+            Some(app.client.cover_url(image))
+        });
+        let new = Snapshot {
+            status: Status::of(app),
+            art_url,
+            seeks: app.seeks,
+        };
+        let old = self.snapshot.lock().unwrap().replace(new.clone());
+        async move {
+            match old {
+                Some(old) => self.signal(&old, &new).await,
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Emits `PropertiesChanged` for what differs between `old` and `new`,
+    /// and `Seeked` after a seek.
+    async fn signal(&self, old: &Snapshot, new: &Snapshot) -> zbus::Result<()> {
+        let player = self
+            .connection
+            .object_server()
+            .interface::<_, Player>(OBJECT_PATH)
+            .await?;
+        let emitter: &SignalEmitter<'_> = player.signal_emitter();
+        if old.seeks != new.seeks {
+            let position = new.status.position.unwrap_or_default();
+            Player::seeked(emitter, (position * 1_000_000.0) as i64).await?;
+        }
+        let (old, new) = (&old.status, &new.status);
+        let player = player.get().await;
+        if old.playback != new.playback {
+            player.playback_status_changed(emitter).await?;
+        }
+        if old.track.as_ref().map(|t| &t._id) != new.track.as_ref().map(|t| &t._id)
+            || old.duration != new.duration
+        {
+            player.metadata_changed(emitter).await?;
+        }
+        if old.volume != new.volume {
+            player.volume_changed(emitter).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::FakePlayer;
+    use crate::radiooo::{tests::sample_track, RadioooClient};
+    use futures::StreamExt;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use tokio::sync::mpsc;
+    use zbus::proxy::CacheProperties;
+
+    /// A private session bus, killed on drop.
+    struct DbusDaemon(Child);
+
+    impl Drop for DbusDaemon {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+        }
+    }
+
+    /// Starts a private bus, `None` if `dbus-daemon` is not installed.
+    fn dbus_daemon() -> Option<(DbusDaemon, String)> {
+        let mut child = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(child.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some((DbusDaemon(child), address.trim().to_string()))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mpris() {
+        let Some((_bus, address)) = dbus_daemon() else {
+            eprintln!("dbus-daemon is not installed, skipping");
+            return;
+        };
+        let (sender, mut events) = mpsc::unbounded_channel();
+        let server = MprisServer::start_at(&address, sender).await.unwrap();
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.play(sample_track());
+        server.update(&app).await.unwrap();
+
+        let connection = connection::Builder::address(address.as_str())
+            .unwrap()
+            .build()
+            .await
+            .unwrap();
+        let player = zbus::proxy::Builder::<zbus::Proxy>::new(&connection)
+            .destination(BUS_NAME)
+            .unwrap()
+            .path(OBJECT_PATH)
+            .unwrap()
+            .interface("org.mpris.MediaPlayer2.Player")
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .unwrap();
+
+        let status: String = player.get_property("PlaybackStatus").await.unwrap();
+        assert_eq!(status, "Playing");
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").await.unwrap();
+        let title: String = metadata["xesam:title"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(title, sample_track().title);
+        let length: i64 = metadata["mpris:length"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(length, 199_000_000);
+        let art_url: String = metadata["mpris:artUrl"]
+            .try_clone()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert!(art_url.starts_with("https://asset.radiooooo.com/"));
+        let volume: f64 = player.get_property("Volume").await.unwrap();
+        assert_eq!(volume, 0.5);
+
+        let _: () = player.call("PlayPause", &()).await.unwrap();
+        player.set_property("Volume", 0.8).await.unwrap();
+        let _: () = player.call("Seek", &(500_000i64)).await.unwrap();
+        let stale = ObjectPath::try_from("/org/radiooooo/track/stale").unwrap();
+        let _: () = player
+            .call("SetPosition", &(stale, 30_000_000i64))
+            .await
+            .unwrap();
+        let current = track_path(&sample_track());
+        let _: () = player
+            .call("SetPosition", &(current, 30_000_000i64))
+            .await
+            .unwrap();
+        for expected in [
+            Control::PlayPause,
+            Control::SetVolume(80),
+            Control::Seek(0.5),
+            Control::SetPosition(30.0),
+        ] {
+            assert!(matches!(events.recv().await, Some(Event::Control(c)) if c == expected));
+        }
+
+        let mut seeked = player.receive_signal("Seeked").await.unwrap();
+        app.tick();
+        app.control(Control::SetPosition(30.0));
+        server.update(&app).await.unwrap();
+        let position: i64 = seeked.next().await.unwrap().body().deserialize().unwrap();
+        assert_eq!(position, 30_000_000);
+    }
+}
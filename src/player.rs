@@ -0,0 +1,230 @@
+use crate::event::Event;
+use libmpv2::events::{Event as MpvEvent, EventContext};
+use libmpv2::{mpv_end_file_reason, Mpv};
+use log::{debug, error};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+/// Error returned by a [`Player`].
+#[derive(Debug, Error)]
+#[error("player error: {0}")]
+pub struct PlayerError(pub String);
+
+impl From<libmpv2::Error> for PlayerError {
+    fn from(err: libmpv2::Error) -> Self {
+        Self(format!("{:?}", err))
+    }
+}
+
+/// Result type of the [`Player`] operations.
+pub type PlayerResult<T> = std::result::Result<T, PlayerError>;
+
+/// Audio engine driven by the application.
+pub trait Player: Send {
+    /// Replaces the current stream with `url` and starts playing it.
+    fn load(&mut self, url: &str) -> PlayerResult<()>;
+    fn pause(&mut self) -> PlayerResult<()>;
+    fn resume(&mut self) -> PlayerResult<()>;
+    /// Stops playback and unloads the current stream.
+    fn stop(&mut self) -> PlayerResult<()>;
+    /// Sets the volume, in percent.
+    fn set_volume(&mut self, volume: u16) -> PlayerResult<()>;
+    fn set_muted(&mut self, muted: bool) -> PlayerResult<()>;
+    /// Position in the current stream, if one is loaded.
+    fn position(&self) -> Option<Duration>;
+    /// Duration of the current stream, once known.
+    fn duration(&self) -> Option<Duration>;
+    /// Moves the position by `offset` seconds, backward if negative.
+    fn seek(&mut self, offset: f64) -> PlayerResult<()>;
+    /// Moves to `position` seconds from the start of the stream.
+    fn seek_to(&mut self, position: f64) -> PlayerResult<()>;
+}
+
+impl Player for Mpv {
+    fn load(&mut self, url: &str) -> PlayerResult<()> {
+        self.command("loadfile", &[url, "replace"])?;
+        self.set_property("pause", false)?;
+        Ok(())
+    }
+
+    fn pause(&mut self) -> PlayerResult<()> {
+        Ok(self.set_property("pause", true)?)
+    }
+
+    fn resume(&mut self) -> PlayerResult<()> {
+        Ok(self.set_property("pause", false)?)
+    }
+
+    fn stop(&mut self) -> PlayerResult<()> {
+        Ok(self.command("stop", &[])?)
+    }
+
+    fn set_volume(&mut self, volume: u16) -> PlayerResult<()> {
+        Ok(self.set_property("volume", i64::from(volume))?)
+    }
+
+    fn set_muted(&mut self, muted: bool) -> PlayerResult<()> {
+        Ok(self.set_property("mute", muted)?)
+    }
+
+    fn position(&self) -> Option<Duration> {
+        self.get_property::<f64>("time-pos")
+            .ok()
+            .filter(|secs| *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.get_property::<f64>("duration")
+            .ok()
+            .filter(|secs| *secs > 0.0)
+            .map(Duration::from_secs_f64)
+    }
+
+    fn seek(&mut self, offset: f64) -> PlayerResult<()> {
+        Ok(self.command("seek", &[&offset.to_string(), "relative"])?)
+    }
+
+    fn seek_to(&mut self, position: f64) -> PlayerResult<()> {
+        Ok(self.command("seek", &[&position.to_string(), "absolute"])?)
+    }
+}
+
+/// Forwards the events of `mpv` to the application from a dedicated thread.
+///
+/// Only the end of a track played to completion, [`Event::TrackEnded`], or
+/// cut by an error, [`Event::TrackFailed`], is reported: a track replaced by
+/// `loadfile` or stopped triggers neither.
+pub fn spawn_mpv_event_listener(mpv: &Mpv, sender: mpsc::UnboundedSender<Event>) {
+    let mut events = EventContext::new(mpv.ctx);
+    if let Err(err) = events.disable_deprecated_events() {
+        error!("failed to disable deprecated mpv events: {:?}", err);
+    }
+    thread::spawn(move || {
+        while !sender.is_closed() {
+            match events.wait_event(1.0) {
+                Some(Ok(MpvEvent::EndFile(reason))) if reason == mpv_end_file_reason::Eof => {
+                    debug!("mpv reached the end of the track");
+                    if sender.send(Event::TrackEnded).is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(MpvEvent::EndFile(reason))) if reason == mpv_end_file_reason::Error => {
+                    error!("mpv failed to play the track");
+                    if sender.send(Event::TrackFailed).is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(MpvEvent::Shutdown)) => break,
+                Some(Err(err)) => error!("mpv event error: {:?}", err),
+                _ => {}
+            }
+        }
+    });
+}
+
+/// State recorded by a [`FakePlayer`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FakePlayerState {
+    pub loaded: Option<String>,
+    pub paused: bool,
+    pub volume: u16,
+    pub muted: bool,
+    pub position: Option<Duration>,
+    pub duration: Option<Duration>,
+}
+
+/// In-memory [`Player`] that only records what it is asked to do.
+///
+/// Clones share the same state, so keep one around to inspect it once the
+/// other has been handed to the [`App`](crate::app::App).
+#[derive(Debug, Clone, Default)]
+pub struct FakePlayer {
+    state: Arc<Mutex<FakePlayerState>>,
+}
+
+impl FakePlayer {
+    /// Constructs a new instance of [`FakePlayer`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pretends the loaded stream lasts `duration`.
+    pub fn set_duration(&self, duration: Duration) {
+        self.state.lock().unwrap().duration = Some(duration);
+    }
+
+    /// Snapshot of the recorded state.
+    pub fn state(&self) -> FakePlayerState {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut FakePlayerState)) -> PlayerResult<()> {
+        f(&mut self.state.lock().unwrap());
+        Ok(())
+    }
+}
+
+impl Player for FakePlayer {
+    fn load(&mut self, url: &str) -> PlayerResult<()> {
+        self.update(|state| {
+            state.loaded = Some(url.to_string());
+            state.paused = false;
+            state.position = Some(Duration::ZERO);
+        })
+    }
+
+    fn pause(&mut self) -> PlayerResult<()> {
+        self.update(|state| state.paused = true)
+    }
+
+    fn resume(&mut self) -> PlayerResult<()> {
+        self.update(|state| state.paused = false)
+    }
+
+    fn stop(&mut self) -> PlayerResult<()> {
+        self.update(|state| {
+            state.loaded = None;
+            state.position = None;
+        })
+    }
+
+    fn set_volume(&mut self, volume: u16) -> PlayerResult<()> {
+        self.update(|state| state.volume = volume)
+    }
+
+    fn set_muted(&mut self, muted: bool) -> PlayerResult<()> {
+        self.update(|state| state.muted = muted)
+    }
+
+    fn position(&self) -> Option<Duration> {
+        self.state.lock().unwrap().position
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.state.lock().unwrap().duration
+    }
+
+    fn seek(&mut self, offset: f64) -> PlayerResult<()> {
+        self.update(|state| {
+            if let Some(position) = state.position {
+                let secs = (position.as_secs_f64() + offset).max(0.0);
+                let secs = state.duration.map_or(secs, |d| secs.min(d.as_secs_f64()));
+                state.position = Some(Duration::from_secs_f64(secs));
+            }
+        })
+    }
+
+    fn seek_to(&mut self, position: f64) -> PlayerResult<()> {
+        self.update(|state| {
+            if state.position.is_some() {
+                let secs = position.max(0.0);
+                let secs = state.duration.map_or(secs, |d| secs.min(d.as_secs_f64()));
+                state.position = Some(Duration::from_secs_f64(secs));
+            }
+        })
+    }
+}
@@ -1,139 +1,583 @@
-use core::panic;
-use log::debug;
-use reqwest::blocking::Client;
-use serde::{de, Deserialize, Serialize};
-use std::{collections::HashMap, io::Read, vec};
-use tokio::{runtime::Handle, task};
-
-pub const DECADES: [i32; 13] = [
-    1900, 1910, 1920, 1930, 1940, 1950, 1960, 1970, 1980, 1990, 2000, 2010, 2020,
-];
-
-pub const MOODS: [&str; 3] = ["SLOW", "WEIRD", "FAST"];
-
-pub const COUNTRY_CODES: [&str; 239] = [
-    "AFG", "ALB", "DZA", "ASM", "AND", "AGO", "AIA", "ATA", "ATG", "ARG", "ARM", "ABW", "AUS",
-    "AUT", "AZE", "BHS", "BHR", "BGD", "BRB", "BLR", "BEL", "BLZ", "BEN", "BMU", "BTN", "BOL",
-    "BIH", "BWA", "BRA", "IOT", "BRN", "BGR", "BFA", "BDI", "CPV", "KHM", "CMR", "CAN", "CYM",
-    "CAF", "TCD", "CHL", "CHN", "CXR", "CCK", "COL", "COM", "COG", "COD", "COK", "CRI", "HRV",
-    "CUB", "CUW", "CYP", "CZE", "DNK", "DJI", "DMA", "DOM", "ECU", "EGY", "SLV", "GNQ", "ERI",
-    "EST", "SWZ", "ETH", "FJI", "FIN", "FRA", "GUF", "PYF", "GAB", "GMB", "GEO", "DEU", "GHA",
-    "GIB", "GRC", "GRL", "GRD", "GLP", "GUM", "GTM", "GGY", "GIN", "GNB", "GUY", "HTI", "HND",
-    "HKG", "HUN", "ISL", "IND", "IDN", "IRN", "IRQ", "IRL", "IMN", "ISR", "ITA", "CIV", "JAM",
-    "JPN", "JEY", "JOR", "KAZ", "KEN", "KIR", "PRK", "KOR", "KWT", "KGZ", "LAO", "LVA", "LBN",
-    "LSO", "LBR", "LBY", "LIE", "LTU", "LUX", "MAC", "MDG", "MWI", "MYS", "MDV", "MLI", "MLT",
-    "MHL", "MTQ", "MRT", "MUS", "MYT", "MEX", "FSM", "MDA", "MCO", "MNG", "MNE", "MSR", "MAR",
-    "MOZ", "MMR", "NAM", "NRU", "NPL", "NLD", "NCL", "NZL", "NIC", "NER", "NGA", "NIU", "NFK",
-    "MKD", "MNP", "NOR", "OMN", "PAK", "PLW", "PSE", "PAN", "PNG", "PRY", "PER", "PHL", "PCN",
-    "POL", "PRT", "PRI", "QAT", "ROU", "RUS", "RWA", "REU", "BLM", "SHN", "KNA", "LCA", "MAF",
-    "SPM", "VCT", "WSM", "SMR", "STP", "SAU", "SEN", "SRB", "SYC", "SLE", "SGP", "SXM", "SVK",
-    "SVN", "SLB", "SOM", "ZAF", "SSD", "ESP", "LKA", "SDN", "SUR", "SJM", "SWE", "CHE", "SYR",
-    "TWN", "TJK", "TZA", "THA", "TLS", "TGO", "TKL", "TON", "TTO", "TUN", "TUR", "TKM", "TCA",
-    "TUV", "UGA", "UKR", "ARE", "GBR", "USA", "URY", "UZB", "VUT", "VEN", "VNM", "VGB", "VIR",
-    "WLF", "ESH", "YEM", "ZMB", "ZWE",
-];
-
-#[derive(Debug, Serialize, Deserialize, Default)]
+use crate::country::{Country, COUNTRIES};
+use log::{debug, warn};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// Error returned when a value is not a valid [`Mood`], [`Decade`] or [`CountryCode`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("unknown mood {0:?}, expected one of SLOW, WEIRD or FAST")]
+    Mood(String),
+    #[error("invalid decade {0:?}, expected a multiple of 10 between 1900 and 2020")]
+    Decade(String),
+    #[error("unknown country code {0:?}, expected an ISO 3166-1 alpha-3 or alpha-2 code")]
+    Country(String),
+    #[error("unknown play mode {0:?}, expected one of explore, shuffle, taxi or islands")]
+    Mode(String),
+}
+
+/// How radiooooo draws the tracks of a request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayMode {
+    /// Stay on a single country and decade.
+    Explore,
+    /// Shuffle across the whole selection.
+    Shuffle,
+    /// Travel across the selection, one track after the other.
+    #[default]
+    Taxi,
+    /// Play from a curated island, see [`ExploreRequest::island`].
+    Islands,
+}
+
+impl PlayMode {
+    pub const ALL: [PlayMode; 4] = [
+        PlayMode::Explore,
+        PlayMode::Shuffle,
+        PlayMode::Taxi,
+        PlayMode::Islands,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlayMode::Explore => "explore",
+            PlayMode::Shuffle => "shuffle",
+            PlayMode::Taxi => "taxi",
+            PlayMode::Islands => "islands",
+        }
+    }
+}
+
+impl fmt::Display for PlayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PlayMode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseError::Mode(s.to_string()))
+    }
+}
+
+/// Mood of a track, as understood by radiooooo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Mood {
+    Slow,
+    Weird,
+    Fast,
+}
+
+impl Mood {
+    pub const ALL: [Mood; 3] = [Mood::Slow, Mood::Weird, Mood::Fast];
+
+    /// Iterates over every mood, in display order.
+    pub fn iter() -> impl Iterator<Item = Mood> {
+        Self::ALL.into_iter()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mood::Slow => "SLOW",
+            Mood::Weird => "WEIRD",
+            Mood::Fast => "FAST",
+        }
+    }
+}
+
+impl fmt::Display for Mood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Mood {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::iter()
+            .find(|mood| mood.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseError::Mood(s.to_string()))
+    }
+}
+
+/// Decade covered by radiooooo, from 1900 to 2020.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub struct Decade(i32);
+
+impl Decade {
+    pub const FIRST: i32 = 1900;
+    pub const LAST: i32 = 2020;
+    pub const ALL: [Decade; 13] = [
+        Decade(1900),
+        Decade(1910),
+        Decade(1920),
+        Decade(1930),
+        Decade(1940),
+        Decade(1950),
+        Decade(1960),
+        Decade(1970),
+        Decade(1980),
+        Decade(1990),
+        Decade(2000),
+        Decade(2010),
+        Decade(2020),
+    ];
+
+    pub fn new(year: i32) -> std::result::Result<Self, ParseError> {
+        if (Self::FIRST..=Self::LAST).contains(&year) && year % 10 == 0 {
+            Ok(Self(year))
+        } else {
+            Err(ParseError::Decade(year.to_string()))
+        }
+    }
+
+    /// Iterates over every decade, oldest first.
+    pub fn iter() -> impl Iterator<Item = Decade> {
+        Self::ALL.into_iter()
+    }
+
+    /// First year of the decade.
+    pub fn year(self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Decade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Decade {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.trim()
+            .parse()
+            .map_err(|_| ParseError::Decade(s.to_string()))
+            .and_then(Self::new)
+    }
+}
+
+impl TryFrom<i32> for Decade {
+    type Error = ParseError;
+
+    fn try_from(year: i32) -> std::result::Result<Self, Self::Error> {
+        Self::new(year)
+    }
+}
+
+impl From<Decade> for i32 {
+    fn from(decade: Decade) -> Self {
+        decade.0
+    }
+}
+
+/// ISO 3166-1 alpha-3 country code, validated against [`COUNTRIES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(into = "String")]
+pub struct CountryCode(&'static str);
+
+impl CountryCode {
+    /// Parses an alpha-3 code, or the alpha-2 code of the same country.
+    pub fn new(code: &str) -> std::result::Result<Self, ParseError> {
+        Country::find(code)
+            .map(|country| Self(country.alpha3))
+            .ok_or_else(|| ParseError::Country(code.to_string()))
+    }
+
+    /// Iterates over every known country code.
+    pub fn iter() -> impl Iterator<Item = CountryCode> {
+        COUNTRIES.iter().map(|country| Self(country.alpha3))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+
+    /// Name, flag and region of the country.
+    pub fn country(self) -> &'static Country {
+        Country::find(self.0).expect("country codes are validated")
+    }
+
+    pub fn name(self) -> &'static str {
+        self.country().name
+    }
+}
+
+impl fmt::Display for CountryCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for CountryCode {
+    type Error = ParseError;
+
+    fn try_from(code: String) -> std::result::Result<Self, Self::Error> {
+        Self::new(&code)
+    }
+}
+
+impl From<CountryCode> for String {
+    fn from(code: CountryCode) -> Self {
+        code.0.to_string()
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Self::new(&code).map_err(de::Error::custom)
+    }
+}
+
+/// Countries having tracks for each mood, for a given decade.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct CountryForDecade {
-    pub SLOW: Vec<String>,
-    pub FAST: Vec<String>,
-    pub WEIRD: Vec<String>,
+    #[serde(rename = "SLOW", deserialize_with = "known_countries")]
+    pub slow: Vec<CountryCode>,
+    #[serde(rename = "FAST", deserialize_with = "known_countries")]
+    pub fast: Vec<CountryCode>,
+    #[serde(rename = "WEIRD", deserialize_with = "known_countries")]
+    pub weird: Vec<CountryCode>,
 }
 
 impl CountryForDecade {
-    pub fn to_hash_map(&self) -> HashMap<&str, Vec<String>> {
-        let mut hm = HashMap::new();
-        hm.insert("SLOW", self.SLOW.clone());
-        hm.insert("FAST", self.FAST.clone());
-        hm.insert("WEIRD", self.WEIRD.clone());
-        hm
+    pub fn get(&self, mood: Mood) -> &[CountryCode] {
+        match mood {
+            Mood::Slow => &self.slow,
+            Mood::Fast => &self.fast,
+            Mood::Weird => &self.weird,
+        }
     }
 }
 
-pub fn get_country_for_decade(decade: i32) -> Option<CountryForDecade> {
-    return task::block_in_place(|| {
-        // Create a new reqwest client
-        let client = Client::new();
+/// Deserializes a list of country codes, dropping the ones we don't know about
+/// instead of failing the whole response.
+fn known_countries<'de, D>(deserializer: D) -> std::result::Result<Vec<CountryCode>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let codes = Vec::<String>::deserialize(deserializer)?;
+    Ok(codes
+        .into_iter()
+        .filter_map(|code| match CountryCode::new(&code) {
+            Ok(code) => Some(code),
+            Err(err) => {
+                warn!("{}", err);
+                None
+            }
+        })
+        .collect())
+}
 
-        // Perform the GET request
-        let response = client
-            .get(format!(
-                "https://radiooooo.com/country/mood?decade={}",
-                decade
-            ))
-            .send()
-            .expect("failed to send https://radiooooo.com/country/mood req");
-
-        // Check if the request was successful
-        if response.status().is_success() {
-            // Deserialize the response body to ApiResponse
-            let api_response: CountryForDecade = response
-                .json()
-                .expect("failed to unmarshall https://radiooooo.com/country/mood req");
-            return Some(api_response);
+/// Errors returned by the radiooooo API layer.
+#[derive(Debug, Error)]
+pub enum RadioooError {
+    /// The request could not be sent or its response could not be read.
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// The API answered with a non-success status.
+    #[error("radiooooo answered {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    /// The response body is not what we expected.
+    #[error("failed to decode response: {0}")]
+    Decode(#[source] serde_json::Error),
+    /// Too many requests were sent, try again later.
+    #[error("rate limited by radiooooo{}", retry_after.map(|d| format!(", retry in {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+    /// There is no track for the requested setting.
+    #[error("no track available for this setting")]
+    NoTrackAvailable,
+}
+
+/// Result type of the radiooooo API layer.
+pub type Result<T> = std::result::Result<T, RadioooError>;
+
+/// Public endpoint of the radiooooo API.
+pub const DEFAULT_BASE_URL: &str = "https://radiooooo.com";
+
+/// CDN serving the covers, [`Image::path`] is relative to it.
+pub const DEFAULT_COVER_BASE_URL: &str = "https://asset.radiooooo.com";
+
+/// Async client for the radiooooo API.
+///
+/// It owns a single connection pool, so clone it instead of building a new one
+/// for every request.
+#[derive(Debug, Clone)]
+pub struct RadioooClient {
+    http: Client,
+    /// Same as `http` without the total timeout, an audio file taking as long
+    /// as the connection needs.
+    streams: Client,
+    base_url: String,
+    cover_base_url: String,
+}
+
+/// Builder for [`RadioooClient`].
+#[derive(Debug, Clone)]
+pub struct RadioooClientBuilder {
+    base_url: String,
+    cover_base_url: String,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: String,
+}
+
+impl Default for RadioooClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            cover_base_url: DEFAULT_COVER_BASE_URL.to_string(),
+            timeout: Some(Duration::from_secs(10)),
+            connect_timeout: Some(Duration::from_secs(5)),
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
         }
-        None
-    });
+    }
 }
 
-pub fn get_track(mood: &str, decade: i32, country: &str) -> Option<Track> {
-    return task::block_in_place(|| {
-        // Perform blocking operation here
+impl RadioooClientBuilder {
+    /// Sets the base URL of the API, e.g. a local mock server.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Sets the base URL of the CDN serving the covers.
+    pub fn cover_base_url(mut self, cover_base_url: impl Into<String>) -> Self {
+        self.cover_base_url = cover_base_url.into().trim_end_matches('/').to_string();
+        self
+    }
 
-        let client = Client::new();
-        // Define your request payload
-        let payload = ExploreRequest {
-            mode: "taxi".to_string(),
-            isocodes: vec![country.to_string()],
-            decades: vec![decade],
-            moods: vec![mood.to_string()],
-        };
+    /// Sets the total timeout of a request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Builds the [`RadioooClient`].
+    pub fn build(self) -> reqwest::Result<RadioooClient> {
+        let mut builder = Client::builder().user_agent(&self.user_agent);
+        let mut streams = Client::builder().user_agent(self.user_agent);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+            streams = streams.connect_timeout(timeout);
+        }
+        Ok(RadioooClient {
+            http: builder.build()?,
+            streams: streams.build()?,
+            base_url: self.base_url,
+            cover_base_url: self.cover_base_url,
+        })
+    }
+}
+
+impl RadioooClient {
+    /// Constructs a new [`RadioooClientBuilder`].
+    pub fn builder() -> RadioooClientBuilder {
+        RadioooClientBuilder::default()
+    }
+
+    /// Base URL the client sends its requests to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub async fn get_country_for_decade(&self, decade: Decade) -> Result<CountryForDecade> {
+        let response = self
+            .http
+            .get(format!("{}/country/mood", self.base_url))
+            .query(&[("decade", decade.year())])
+            .send()
+            .await?;
+        decode(response).await
+    }
+
+    /// Draws a track matching `request`.
+    pub async fn get_track(&self, request: &ExploreRequest) -> Result<Track> {
+        let response = self
+            .http
+            .post(format!("{}/play", self.base_url))
+            .json(request)
+            .send()
+            .await?;
+        if matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::NO_CONTENT
+        ) {
+            return Err(RadioooError::NoTrackAvailable);
+        }
+
+        let track: Track = decode(response).await?;
+        debug!("req: {:?}=>{}", request, track._id);
+        Ok(track)
+    }
+
+    /// URL of a cover image on the CDN.
+    pub fn cover_url(&self, image: &Image) -> String {
+        format!(
+            "{}/{}{}",
+            self.cover_base_url,
+            image.path.trim_start_matches('/'),
+            image.filename
+        )
+    }
+
+    /// Downloads the raw bytes of a cover image.
+    pub async fn get_cover(&self, image: &Image) -> Result<Vec<u8>> {
+        self.get_bytes(&self.cover_url(image)).await
+    }
+
+    /// Requests an audio stream, `url` being one of the [`Links`] of a track.
+    ///
+    /// The body is left to read with [`reqwest::Response::chunk`], the total
+    /// timeout of the client does not apply to it.
+    pub async fn get_stream(&self, url: &str) -> Result<reqwest::Response> {
+        let response = self.streams.get(url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(RadioooError::Status { status, body });
+        }
+        Ok(response)
+    }
+
+    async fn get_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.http.get(url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(RadioooError::Status { status, body });
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
 
-        let payload_json = serde_json::to_string(&payload).expect("failed to marshall");
-        // Send the POST request
-        let response = client
-            .post("https://radiooooo.com/play")
-            .json(&payload) // Serialize the payload to JSON
+    /// Fetches `id` again, with fresh stream links.
+    pub async fn get_track_by_id(&self, id: &str) -> Result<Track> {
+        let response = self
+            .http
+            .get(format!("{}/track/play/{}", self.base_url, id))
             .send()
-            .expect("an error happened");
-
-        // Ensure the request was successful
-        if response.status().is_success() {
-            // Parse the JSON response
-            // debug!("rsp: {:?}", response.text().unwrap());
-
-            let api_response: Track = response
-                .json()
-                .expect("should not have a problem unmarshalling json");
-            debug!("req: {} {} {}=>{}", mood, decade, country, api_response._id);
-            // return None;
-            return Some(api_response);
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(RadioooError::NoTrackAvailable);
         }
+        decode(response).await
+    }
+}
+
+/// Checks the status of an API response and deserializes its body.
+async fn decode<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs);
+        return Err(RadioooError::RateLimited { retry_after });
+    }
 
-        panic!("{:?} {:?}", payload_json, response);
-    });
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(RadioooError::Status { status, body });
+    }
+    serde_json::from_str(&body).map_err(RadioooError::Decode)
 }
 
-#[derive(Serialize, Debug, PartialEq)]
-struct ExploreRequest {
-    mode: String,
-    isocodes: Vec<String>,
-    decades: Vec<i32>,
-    moods: Vec<String>,
+/// Body of a `/play` request: the track is drawn from any combination of
+/// the given moods, decades and countries.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExploreRequest {
+    pub mode: PlayMode,
+    pub isocodes: Vec<CountryCode>,
+    pub decades: Vec<Decade>,
+    pub moods: Vec<Mood>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub island: Option<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+impl ExploreRequest {
+    /// Constructs a new [`ExploreRequest`] in [`PlayMode::Taxi`].
+    pub fn new(moods: Vec<Mood>, decades: Vec<Decade>, isocodes: Vec<CountryCode>) -> Self {
+        Self {
+            mode: PlayMode::default(),
+            isocodes,
+            decades,
+            moods,
+            island: None,
+        }
+    }
+
+    /// Sets the play mode.
+    ///
+    /// [`PlayMode::Explore`] stays on the first country and decade only.
+    pub fn mode(mut self, mode: PlayMode) -> Self {
+        self.mode = mode;
+        if mode == PlayMode::Explore {
+            self.isocodes.truncate(1);
+            self.decades.truncate(1);
+        }
+        self
+    }
+
+    /// Sets the island to play from, used by [`PlayMode::Islands`].
+    pub fn island(mut self, island: impl Into<String>) -> Self {
+        self.island = Some(island.into());
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Track {
     pub _id: String,
-    pub mood: String,
+    pub mood: Mood,
     pub title: String,
     pub artist: String,
     pub album: Option<String>,
     pub songwriter: Option<String>, // This field seems to be always present but can be an empty string
     pub label: Option<String>,
-    pub country: String,
+    pub country: CountryCode,
     pub year: String,
-    pub decade: i32,
+    pub decade: Decade,
     pub length: u32,
     pub uuid: String,
     pub ext: Option<Ext>,
@@ -146,33 +590,64 @@ pub struct Track {
     pub links: Links,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Ext {
     track: String,
     cover: Option<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Image {
     path: String,
     filename: String,
     color: Option<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+impl Image {
+    /// Directory of the image on the CDN, e.g. `cover/ARG/1970/`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Dominant color of the image, as `#rrggbb`.
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Links {
     pub mpeg: String,
     pub ogg: String,
 }
 
+impl Links {
+    /// Time after which the CDN stops serving the links, from their `expires` parameter.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.mpeg
+            .split(['?', '&'])
+            .find_map(|param| param.strip_prefix("expires="))
+            .and_then(|secs| secs.parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
+    }
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use serde_json;
+    use wiremock::matchers::{body_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
-    #[test]
-    fn test_track_deserialization() {
-        let json_data = r##"
+    pub(crate) const TRACK_JSON: &str = r##"
         {
   "_id": "5d330a5a06fb03d8872a5d58",
   "mood": "FAST",
@@ -210,17 +685,24 @@ mod tests {
   }
 }"##;
 
+    /// Track decoded from [`TRACK_JSON`], for tests of other modules.
+    pub(crate) fn sample_track() -> Track {
+        serde_json::from_str(TRACK_JSON).unwrap()
+    }
+
+    #[test]
+    fn test_track_deserialization() {
         let expected_track = Track {
             _id: "5d330a5a06fb03d8872a5d58".to_string(),
-            mood: "FAST".to_string(),
+            mood: Mood::Fast,
             title: "Busco El Sol, No Sé Adonde Voy".to_string(),
             artist: "Caballo Vapor".to_string(),
             album: Some("Busco El Sol, No Sé Adonde Voy SP".to_string()),
             songwriter: Some("".to_string()),
             label: Some("MH".to_string()),
-            country: "ARG".to_string(),
+            country: CountryCode::new("ARG").unwrap(),
             year: "1975".to_string(),
-            decade: 1970,
+            decade: Decade::new(1970).unwrap(),
             length: 199,
             uuid: "380ca57f-188e-4795-9b17-f1721a7e8188".to_string(),
             ext: Some(Ext {
@@ -247,8 +729,186 @@ mod tests {
             },
         };
 
-        let track: Track = serde_json::from_str(json_data).expect("Failed to deserialize JSON");
+        let track: Track = serde_json::from_str(TRACK_JSON).expect("Failed to deserialize JSON");
 
         assert_eq!(track, expected_track);
     }
+
+    #[test]
+    fn test_domain_types() {
+        assert_eq!("weird".parse(), Ok(Mood::Weird));
+        assert!("groovy".parse::<Mood>().is_err());
+        assert_eq!(Mood::iter().count(), 3);
+
+        assert_eq!("1970".parse(), Ok(Decade(1970)));
+        assert!(Decade::new(1975).is_err());
+        assert!(Decade::new(2030).is_err());
+        assert_eq!(Decade::iter().last(), Some(Decade(2020)));
+
+        assert_eq!("civ".parse(), Ok(CountryCode("CIV")));
+        assert!(CountryCode::new("XXX").is_err());
+        assert_eq!(CountryCode::iter().count(), COUNTRIES.len());
+        assert_eq!("ar".parse(), Ok(CountryCode("ARG")));
+
+        let ca: CountryForDecade =
+            serde_json::from_str(r#"{"SLOW":["ARG","XXX"],"FAST":[],"WEIRD":["JPN"]}"#).unwrap();
+        assert_eq!(ca.get(Mood::Slow), [CountryCode("ARG")]);
+        assert_eq!(ca.get(Mood::Weird), [CountryCode("JPN")]);
+        assert!(serde_json::from_str::<Decade>("1971").is_err());
+        assert_eq!(serde_json::to_string(&Mood::Slow).unwrap(), r#""SLOW""#);
+
+        assert_eq!("Shuffle".parse(), Ok(PlayMode::Shuffle));
+        assert!("walk".parse::<PlayMode>().is_err());
+    }
+
+    #[test]
+    fn test_explore_request_modes() {
+        let request = ExploreRequest::new(
+            vec![Mood::Slow],
+            vec![Decade(1960), Decade(1970)],
+            vec![CountryCode("FRA"), CountryCode("ITA")],
+        );
+        assert_eq!(
+            serde_json::to_value(request.clone().mode(PlayMode::Shuffle)).unwrap(),
+            serde_json::json!({
+                "mode": "shuffle",
+                "isocodes": ["FRA", "ITA"],
+                "decades": [1960, 1970],
+                "moods": ["SLOW"],
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(request.mode(PlayMode::Explore)).unwrap(),
+            serde_json::json!({
+                "mode": "explore",
+                "isocodes": ["FRA"],
+                "decades": [1960],
+                "moods": ["SLOW"],
+            })
+        );
+        let island = ExploreRequest::new(vec![Mood::Fast], vec![], vec![])
+            .mode(PlayMode::Islands)
+            .island("5d330a5a");
+        assert_eq!(
+            serde_json::to_value(island).unwrap()["island"],
+            serde_json::json!("5d330a5a")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .and(query_param("decade", "1970"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"SLOW":["ARG"],"FAST":["ARG","FRA"],"WEIRD":[]}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .and(body_json(serde_json::json!({
+                "mode": "taxi",
+                "isocodes": ["ARG"],
+                "decades": [1970],
+                "moods": ["FAST"],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(TRACK_JSON, "application/json"))
+            .mount(&server)
+            .await;
+
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+
+        let seventies = Decade::new(1970).unwrap();
+        let argentina = CountryCode::new("ARG").unwrap();
+        let countries = client.get_country_for_decade(seventies).await.unwrap();
+        assert_eq!(
+            countries.get(Mood::Fast),
+            [argentina, CountryCode::new("FRA").unwrap()]
+        );
+
+        let track = client
+            .get_track(&ExploreRequest::new(
+                vec![Mood::Fast],
+                vec![seventies],
+                vec![argentina],
+            ))
+            .await
+            .unwrap();
+        assert_eq!(track.uuid, "380ca57f-188e-4795-9b17-f1721a7e8188");
+    }
+
+    #[tokio::test]
+    async fn test_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .and(query_param("decade", "1900"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .and(query_param("decade", "1910"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/country/mood"))
+            .and(query_param("decade", "1920"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/play"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let client = RadioooClient::builder()
+            .base_url(server.uri())
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            client.get_country_for_decade(Decade(1900)).await,
+            Err(RadioooError::Decode(_))
+        ));
+        assert!(matches!(
+            client.get_country_for_decade(Decade(1910)).await,
+            Err(RadioooError::RateLimited {
+                retry_after: Some(d)
+            }) if d == Duration::from_secs(30)
+        ));
+        assert!(matches!(
+            client.get_country_for_decade(Decade(1920)).await,
+            Err(RadioooError::Status { status, body }) if status == 500 && body == "boom"
+        ));
+        assert!(matches!(
+            client
+                .get_track(&ExploreRequest::new(
+                    vec![Mood::Fast],
+                    vec![Decade(1970)],
+                    vec![CountryCode("ARG")]
+                ))
+                .await,
+            Err(RadioooError::NoTrackAvailable)
+        ));
+
+        let unreachable = RadioooClient::builder()
+            .base_url("http://127.0.0.1:1")
+            .build()
+            .unwrap();
+        assert!(matches!(
+            unreachable
+                .get_track(&ExploreRequest::new(vec![Mood::Fast], vec![], vec![]))
+                .await,
+            Err(RadioooError::Network(_))
+        ));
+    }
 }
@@ -0,0 +1,348 @@
+use crate::radiooo::Track;
+use log::{debug, error, info};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// Public ListenBrainz API, other servers implement the same protocol.
+pub const DEFAULT_URL: &str = "https://api.listenbrainz.org";
+
+/// A track becomes a listen once half of it, or four minutes, were played.
+pub const MAX_THRESHOLD: Duration = Duration::from_secs(4 * 60);
+
+/// Most listens accepted by one request.
+const MAX_LISTENS_PER_REQUEST: usize = 1000;
+
+/// Error returned while submitting listens.
+#[derive(Debug, Error)]
+pub enum ScrobbleError {
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("the scrobbling server answered {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("failed to access the scrobble queue {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, ScrobbleError>;
+
+/// Track played at `listened_at`, in the ListenBrainz format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Listen {
+    /// Seconds since the epoch, when the track started.
+    pub listened_at: u64,
+    pub track_metadata: TrackMetadata,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackMetadata {
+    pub artist_name: String,
+    pub track_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_name: Option<String>,
+    pub additional_info: AdditionalInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdditionalInfo {
+    pub duration_ms: u64,
+    pub media_player: String,
+    pub submission_client: String,
+    pub submission_client_version: String,
+}
+
+impl From<&Track> for TrackMetadata {
+    fn from(track: &Track) -> Self {
+        Self {
+            artist_name: track.artist.clone(),
+            track_name: track.title.clone(),
+            release_name: track.album.clone().filter(|album| !album.is_empty()),
+            additional_info: AdditionalInfo {
+                duration_ms: u64::from(track.length) * 1000,
+                media_player: String::from("radiooooo"),
+                submission_client: env!("CARGO_PKG_NAME").to_string(),
+                submission_client_version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+        }
+    }
+}
+
+/// Playback time after which a track of `length` is submitted as a listen.
+pub fn threshold(length: Duration) -> Duration {
+    (length / 2).min(MAX_THRESHOLD)
+}
+
+/// Seconds since the epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Listens not accepted yet, persisted as JSON.
+#[derive(Debug)]
+struct Queue {
+    path: Option<PathBuf>,
+    listens: Vec<Listen>,
+}
+
+impl Queue {
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let io_error = |source| ScrobbleError::Io {
+            path: path.clone(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let json = serde_json::to_vec_pretty(&self.listens).expect("listens are serializable");
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(io_error)?;
+        fs::rename(&tmp, path).map_err(io_error)
+    }
+}
+
+/// Submits the played tracks to a ListenBrainz compatible server.
+///
+/// Listens that cannot be sent are queued, on disk when a queue file is set,
+/// and sent again by the next [`Scrobbler::flush`]. Clones share the queue.
+#[derive(Debug, Clone)]
+pub struct Scrobbler {
+    http: Client,
+    url: String,
+    token: String,
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Scrobbler {
+    /// Submits to the server at `url`, authenticated by the user `token`.
+    pub fn new(url: &str, token: impl Into<String>) -> reqwest::Result<Self> {
+        let http = Client::builder()
+            .user_agent(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        Ok(Self {
+            http,
+            url: url.trim_end_matches('/').to_string(),
+            token: token.into(),
+            queue: Arc::new(Mutex::new(Queue {
+                path: None,
+                listens: Vec::new(),
+            })),
+        })
+    }
+
+    /// `$XDG_DATA_HOME/radiooooo/scrobbles.json`.
+    pub fn default_queue_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("radiooooo").join("scrobbles.json"))
+    }
+
+    /// Keeps the queue in `path`, loading the listens left by a previous run.
+    ///
+    /// A corrupted queue is moved aside and started again empty.
+    pub fn queue_file(self, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let listens = match fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(listens) => listens,
+                Err(err) => {
+                    let aside = path.with_extension("json.corrupted");
+                    error!(
+                        "the scrobble queue {} is corrupted, moved to {}: {}",
+                        path.display(),
+                        aside.display(),
+                        err
+                    );
+                    if let Err(source) = fs::rename(&path, &aside) {
+                        return Err(ScrobbleError::Io { path, source });
+                    }
+                    Vec::new()
+                }
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(source) => return Err(ScrobbleError::Io { path, source }),
+        };
+        *self.queue.try_lock().expect("the queue is not shared yet") = Queue {
+            path: Some(path),
+            listens,
+        };
+        Ok(self)
+    }
+
+    /// Number of listens waiting to be sent.
+    pub async fn pending(&self) -> usize {
+        self.queue.lock().await.listens.len()
+    }
+
+    /// Tells the server `track` just started, it is not retried.
+    pub async fn now_playing(&self, track: &Track) -> Result<()> {
+        let payload = json!([{ "track_metadata": TrackMetadata::from(track) }]);
+        self.submit("playing_now", payload).await
+    }
+
+    /// Queues `track` as listened from `listened_at` and sends the queue.
+    pub async fn scrobble(&self, track: &Track, listened_at: u64) -> Result<()> {
+        {
+            let mut queue = self.queue.lock().await;
+            queue.listens.push(Listen {
+                listened_at,
+                track_metadata: TrackMetadata::from(track),
+            });
+            queue.save()?;
+        }
+        self.flush().await
+    }
+
+    /// Sends the queued listens, those the server rejects as invalid are
+    /// dropped rather than retried forever.
+    pub async fn flush(&self) -> Result<()> {
+        let mut queue = self.queue.lock().await;
+        while !queue.listens.is_empty() {
+            let count = queue.listens.len().min(MAX_LISTENS_PER_REQUEST);
+            let listen_type = if count == 1 { "single" } else { "import" };
+            let payload = json!(&queue.listens[..count]);
+            match self.submit(listen_type, payload).await {
+                Ok(()) => info!("submitted {} listens", count),
+                Err(ScrobbleError::Status { status, body })
+                    if status == StatusCode::BAD_REQUEST =>
+                {
+                    error!(
+                        "dropping {} listens rejected by the server: {}",
+                        count, body
+                    );
+                }
+                Err(err) => return Err(err),
+            }
+            queue.listens.drain(..count);
+            queue.save()?;
+        }
+        Ok(())
+    }
+
+    async fn submit(&self, listen_type: &str, payload: serde_json::Value) -> Result<()> {
+        debug!("submit-listens {}: {}", listen_type, payload);
+        let response = self
+            .http
+            .post(format!("{}/1/submit-listens", self.url))
+            .header("Authorization", format!("Token {}", self.token))
+            .json(&json!({ "listen_type": listen_type, "payload": payload }))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await?;
+            return Err(ScrobbleError::Status { status, body });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radiooo::tests::sample_track;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_threshold() {
+        assert_eq!(
+            threshold(Duration::from_secs(200)),
+            Duration::from_secs(100)
+        );
+        assert_eq!(threshold(Duration::from_secs(900)), MAX_THRESHOLD);
+    }
+
+    #[tokio::test]
+    async fn test_scrobble() {
+        let server = MockServer::start().await;
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join("scrobbles.json");
+        let scrobbler = Scrobbler::new(&server.uri(), "secret")
+            .unwrap()
+            .queue_file(&queue)
+            .unwrap();
+        let track = sample_track();
+
+        Mock::given(method("POST"))
+            .and(path("/1/submit-listens"))
+            .and(header("Authorization", "Token secret"))
+            .and(body_partial_json(json!({
+                "listen_type": "playing_now",
+                "payload": [{ "track_metadata": {
+                    "artist_name": track.artist,
+                    "track_name": track.title,
+                    "additional_info": { "duration_ms": 199_000 },
+                }}],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        scrobbler.now_playing(&track).await.unwrap();
+
+        // the server is down, the listen stays queued on disk
+        let unavailable = Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount_as_scoped(&server)
+            .await;
+        assert!(scrobbler.scrobble(&track, 1_700_000_000).await.is_err());
+        drop(unavailable);
+        assert_eq!(scrobbler.pending().await, 1);
+
+        let reopened = Scrobbler::new(&server.uri(), "secret")
+            .unwrap()
+            .queue_file(&queue)
+            .unwrap();
+        assert_eq!(reopened.pending().await, 1);
+
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({
+                "listen_type": "single",
+                "payload": [{ "listened_at": 1_700_000_000 }],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        reopened.flush().await.unwrap();
+        assert_eq!(reopened.pending().await, 0);
+        assert_eq!(fs::read_to_string(&queue).unwrap(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_queue_is_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join("scrobbles.json");
+        fs::write(&queue, "[{").unwrap();
+        let scrobbler = Scrobbler::new(DEFAULT_URL, "secret")
+            .unwrap()
+            .queue_file(&queue)
+            .unwrap();
+        assert_eq!(scrobbler.pending().await, 0);
+        assert!(!queue.exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("scrobbles.json.corrupted")).unwrap(),
+            "[{"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app::{App, AppResult};
+use crate::cover::Overlay;
 use crate::event::EventHandler;
 use crate::ui;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
@@ -18,12 +19,18 @@ pub struct Tui<B: Backend> {
     terminal: Terminal<B>,
     /// Terminal event handler.
     pub events: EventHandler,
+    /// Covers drawn with escape sequences, written after each frame.
+    overlay: Overlay,
 }
 
 impl<B: Backend> Tui<B> {
     /// Constructs a new instance of [`Tui`].
     pub fn new(terminal: Terminal<B>, events: EventHandler) -> Self {
-        Self { terminal, events }
+        Self {
+            terminal,
+            events,
+            overlay: Overlay::default(),
+        }
     }
 
     /// Initializes the terminal interface.
@@ -51,7 +58,9 @@ impl<B: Backend> Tui<B> {
     /// [`Draw`]: ratatui::Terminal::draw
     /// [`rendering`]: crate::ui::render
     pub fn draw(&mut self, app: &mut App) -> AppResult<()> {
-        self.terminal.draw(|frame| ui::render(app, frame))?;
+        let screen = self.terminal.draw(|frame| ui::render(app, frame))?.area;
+        self.overlay
+            .write(&mut io::stderr(), screen, app.visible_cover())?;
         Ok(())
     }
 
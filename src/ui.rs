@@ -1,20 +1,42 @@
-use std::{borrow::BorrowMut, vec};
+use std::{borrow::Cow, time::Duration, vec};
 
 use ratatui::{
     layout::Alignment,
     prelude::*,
     style::{Color, Style},
     widgets::*,
-    widgets::{Block, BorderType, Padding, Paragraph},
+    widgets::{Block, Padding, Paragraph},
     Frame,
 };
-use serde_json::to_string;
 
-use crate::app::{self, App, PlayState};
+use crate::app::{self, App, PlayState, SelectedList};
+use crate::cover::CoverWidget;
+use crate::keymap::Action;
 use crate::radiooo;
 
+/// Where the widgets the mouse acts on were last drawn, see
+/// [`crate::handler::handle_mouse_events`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Areas {
+    pub play_state: Rect,
+    pub volume: Rect,
+    /// Moods, decades and countries lists, empty out of the browse view.
+    pub lists: Vec<(SelectedList, Rect)>,
+}
+
+impl Areas {
+    /// List drawn at `position`, with its area.
+    pub fn list_at(&self, position: Position) -> Option<(SelectedList, Rect)> {
+        self.lists
+            .iter()
+            .copied()
+            .find(|(_, area)| area.contains(position))
+    }
+}
+
 /// Renders the user interface widgets.
 pub fn render(app: &mut App, frame: &mut Frame) {
+    app.areas = Areas::default();
     // master layout
     let master_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -22,6 +44,7 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             Constraint::Length(2),
             Constraint::Fill(10),
             Constraint::Length(1),
+            Constraint::Length(1),
         ])
         .split(frame.size());
 
@@ -33,10 +56,12 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     ])
     .split(master_layout[0]);
 
-    let body_constraints = match app.play_state {
-        PlayState::Paused(_) => vec![Constraint::Fill(1)],
-        PlayState::Playing(_) => vec![Constraint::Fill(1), Constraint::Percentage(40)],
-        PlayState::Stopped => vec![Constraint::Fill(1)],
+    let current_track = app.play_state.track().cloned();
+    let show_side_panel = current_track.is_some() || !app.history.is_empty();
+    let body_constraints = if show_side_panel {
+        vec![Constraint::Fill(1), Constraint::Percentage(40)]
+    } else {
+        vec![Constraint::Fill(1)]
     };
     let body_layout = Layout::horizontal(body_constraints).split(master_layout[1]);
 
@@ -46,15 +71,23 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
     // play state
     frame.render_widget(
-        render_play_state(&app.play_state)
+        render_play_state(app)
             .alignment(Alignment::Left)
             .block(Block::new().padding(Padding::left(1))),
         header_layout[0],
     );
+    app.areas.play_state = header_layout[0];
 
-    // request state
+    // request state, replaced by the last error if any
+    let request_state = match &app.error {
+        Some(err) => Line::from(err.as_str().red()),
+        None => Line::from(vec![
+            app.current_setting.as_str().into(),
+            format!(" [{}]", app.mode).dim(),
+        ]),
+    };
     frame.render_widget(
-        Paragraph::new(app.current_setting.as_str())
+        Paragraph::new(request_state)
             .alignment(Alignment::Center)
             .block(Block::new().padding(Padding::right(1))),
         header_layout[1],
@@ -67,94 +100,405 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             .block(Block::new().padding(Padding::right(1))),
         header_layout[2],
     );
+    app.areas.volume = header_layout[2];
 
     /////////////////////////////////////
     // body
     /////////////////////////////////////
 
+    match app.view {
+        app::View::Browse => render_lists(app, frame, body_layout[0]),
+        app::View::Favorites => render_favorites(app, frame, body_layout[0]),
+    }
+
+    if show_side_panel {
+        let side_constraints = if current_track.is_some() {
+            vec![
+                Constraint::Length(TRACK_DETAILS_HEIGHT),
+                Constraint::Fill(1),
+            ]
+        } else {
+            vec![Constraint::Fill(1)]
+        };
+        let side_layout = Layout::vertical(side_constraints).split(body_layout[1]);
+
+        if let Some(track) = &current_track {
+            let details_layout = match app.cover {
+                Some(_) => {
+                    Layout::horizontal(vec![Constraint::Length(COVER_WIDTH), Constraint::Fill(1)])
+                        .split(side_layout[0])
+                }
+                None => Layout::horizontal(vec![Constraint::Fill(1)]).split(side_layout[0]),
+            };
+            if let Some(cover) = app.cover.as_mut() {
+                let area = Block::bordered().inner(details_layout[0]);
+                frame.render_widget(Block::bordered().title("Cover"), details_layout[0]);
+                frame.render_stateful_widget(CoverWidget::new(app.graphics), area, cover);
+            }
+            frame.render_widget(
+                render_track_details(track, app.is_liked()),
+                details_layout[details_layout.len() - 1],
+            );
+        }
+
+        // history, most recent first
+        let mut history_state = ListState::default()
+            .with_selected(app.history_cursor.map(|i| app.history.len() - 1 - i));
+        frame.render_stateful_widget(
+            List::new(
+                app.history
+                    .iter()
+                    .rev()
+                    .map(|track| format!("{} - {}", track.artist, track.title)),
+            )
+            .block(Block::bordered().title("History"))
+            .highlight_style(Style::new().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> "),
+            side_layout[side_layout.len() - 1],
+            &mut history_state,
+        );
+    }
+
+    /////////////////////////////////////
+    // progress
+    /////////////////////////////////////
+    if let (Some(position), Some(duration)) = (app.position, app.duration) {
+        frame.render_widget(render_progress(position, duration), master_layout[2]);
+    }
+
+    /////////////////////////////////////
+    // footer
+    /////////////////////////////////////
+    let footer = match app.fresh_notice() {
+        // typed keys go to the query, the filter keys matter more
+        _ if app.filter.is_some() => render_help(FILTER_HELP),
+        Some(notice) => Line::styled(notice.to_string(), Color::Green),
+        None => render_help(app.keymap.help(&footer_actions(app))),
+    };
+    frame.render_widget(Paragraph::new(footer).centered(), master_layout[3]);
+
+    if app.show_help {
+        render_help_popup(app, frame);
+    }
+}
+
+/// Actions listed in the footer, those of the focused pane then those of
+/// the playback, in order.
+fn footer_actions(app: &App) -> Vec<Action> {
+    let mut actions = match app.view {
+        app::View::Browse => {
+            let marked = match app.list_selected {
+                SelectedList::Mood => !app.marked_moods.is_empty(),
+                SelectedList::Decade => !app.marked_decades.is_empty(),
+                SelectedList::Country => !app.marked_countries.is_empty(),
+            };
+            let mut actions = vec![Action::Select, Action::ToggleMark];
+            if marked {
+                actions.push(Action::ClearMarks);
+            }
+            actions.extend([Action::Filter, Action::CycleMode, Action::Favorites]);
+            actions
+        }
+        app::View::Favorites => vec![Action::Select, Action::Save, Action::Back],
+    };
+    match app.play_state {
+        PlayState::Playing(_) | PlayState::Paused(_) => {
+            actions.extend([Action::PlayPause, Action::NextTrack]);
+            if app.history_cursor.is_some_and(|i| i > 0) {
+                actions.push(Action::PreviousTrack);
+            }
+            actions.push(Action::ToggleFavorite);
+            if app.view == app::View::Browse {
+                actions.push(Action::Save);
+            }
+        }
+        PlayState::Stopped => {}
+    }
+    actions.extend([Action::Help, Action::Quit]);
+    actions
+}
+
+/// Every action with its keys, over the whole screen.
+fn render_help_popup(app: &App, frame: &mut Frame) {
+    let area = frame.size().inner(Margin::new(2, 1));
+    let rows = Action::ALL.into_iter().map(|action| {
+        let keys = app
+            .keymap
+            .keys(action)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        Row::new(vec![
+            Cell::from(keys).bold(),
+            Cell::from(action.description()),
+            Cell::from(action.details()).dim(),
+        ])
+    });
+    let close = app.keymap.help(&[Action::Help, Action::Back]);
+    let close = close
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>()
+        .join("/");
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(["Keys", "Action", ""]).underlined())
+    .block(
+        Block::bordered()
+            .title("Help")
+            .title_bottom(Line::from(format!(" {close} to close ")).right_aligned())
+            .padding(Padding::horizontal(1)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(table, area);
+}
+
+/// Footer while a list is filtered, typed keys are not looked up in the keymap.
+const FILTER_HELP: [(&str, &str); 4] = [
+    ("↑/↓", "move"),
+    ("enter", "select"),
+    ("backspace", "erase"),
+    ("esc", "cancel"),
+];
+
+/// `key label` pairs of the footer.
+fn render_help<'a, K: Into<Cow<'static, str>>>(
+    help: impl IntoIterator<Item = (K, &'a str)>,
+) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (key, label) in help {
+        if !spans.is_empty() {
+            spans.push(Span::raw("  "));
+        }
+        spans.push(Span::styled(key, Style::new().bold()));
+        spans.push(Span::styled(format!(" {label}"), Style::new().dim()));
+    }
+    Line::from(spans)
+}
+
+/// Frames of the spinner shown while a track is requested.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Duration of a frame of the spinner, the default tick rate.
+const SPINNER_FRAME: Duration = Duration::from_millis(250);
+
+fn render_play_state(app: &App) -> Paragraph<'static> {
+    // .fg(Color::from_u32(233))
+    // .bg(Color::from_str("#FF5F87")
+    //     .expect("this is a valid color that should be recognised at runtime"))
+
+    if let Some((_, since)) = &app.tuning {
+        let frame = since.elapsed().as_millis() / SPINNER_FRAME.as_millis();
+        let spinner = SPINNER[frame as usize % SPINNER.len()];
+        return Paragraph::new(format!("{spinner} tuning…"));
+    }
+    match &app.play_state {
+        PlayState::Paused(_) => Paragraph::new("Paused"),
+        PlayState::Playing(_) => Paragraph::new("Playing"),
+        PlayState::Stopped => Paragraph::new("Stopped"),
+    }
+}
+
+/// Renders the moods, decades and countries lists.
+fn render_lists(app: &mut App, frame: &mut Frame, area: Rect) {
     let lists_layout = Layout::horizontal(vec![
         Constraint::Fill(1),
         Constraint::Fill(1),
         Constraint::Fill(1),
     ])
-    .split(body_layout[0]);
+    .split(area);
 
-    frame.render_stateful_widget(
-        List::new(radiooo::MOODS)
-            .block(
-                get_block_style_selector(app.list_selected, app::SelectedList::Mood).title("Moods"),
-            )
-            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
-            .highlight_symbol(">>")
-            .repeat_highlight_symbol(true),
-        lists_layout[0],
-        app.mood_state.borrow_mut(),
-    );
+    let moods = radiooo::Mood::iter()
+        .map(|m| render_list_item(m.to_string(), app.marked_moods.contains(&m)))
+        .collect();
+    let decades = radiooo::Decade::iter()
+        .map(|d| render_list_item(d.to_string(), app.marked_decades.contains(&d)))
+        .collect();
+    // nothing to show until the availability arrives
+    let countries_title = if app.availability_request.is_some() && app.country_availables.is_empty()
+    {
+        "Countries loading…"
+    } else {
+        "Countries"
+    };
+    let countries = app
+        .get_countries_available()
+        .into_iter()
+        .map(|c| render_list_item(c.country().to_string(), app.marked_countries.contains(&c)))
+        .collect();
+    let lists = [
+        (SelectedList::Mood, "Moods", app.marked_moods.len(), moods),
+        (
+            SelectedList::Decade,
+            "Decades",
+            app.marked_decades.len(),
+            decades,
+        ),
+        (
+            SelectedList::Country,
+            countries_title,
+            app.marked_countries.len(),
+            countries,
+        ),
+    ];
+    for ((list, title, marked, items), area) in lists.into_iter().zip(lists_layout.iter()) {
+        render_list(app, frame, *area, list, list_title(title, marked), items);
+        app.areas.lists.push((list, *area));
+    }
+}
 
+/// Renders one of the lists, narrowed to the matches while it is filtered.
+fn render_list(
+    app: &mut App,
+    frame: &mut Frame,
+    area: Rect,
+    list: SelectedList,
+    title: String,
+    items: Vec<ListItem<'static>>,
+) {
+    let block = get_block_style_selector(app.list_selected, list);
+    let (items, title, state) = match &mut app.filter {
+        Some(filter) if filter.list == list => (
+            filter.matches.iter().map(|&i| items[i].clone()).collect(),
+            format!("{} /{}", title, filter.query),
+            &mut filter.state,
+        ),
+        _ => (
+            items,
+            title,
+            match list {
+                SelectedList::Mood => &mut app.mood_state,
+                SelectedList::Decade => &mut app.decade_state,
+                SelectedList::Country => &mut app.country_state,
+            },
+        ),
+    };
     frame.render_stateful_widget(
-        List::new(radiooo::DECADES.map(|n| n.to_string()))
-            .block(
-                get_block_style_selector(app.list_selected, app::SelectedList::Decade)
-                    .title("Decades"),
-            )
+        List::new(items)
+            .block(block.title(title))
             .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
             .highlight_symbol(">>")
             .repeat_highlight_symbol(true),
-        lists_layout[1],
-        app.decade_state.borrow_mut(),
+        area,
+        state,
     );
+}
 
+/// Renders the liked tracks, most recent last.
+fn render_favorites(app: &mut App, frame: &mut Frame, area: Rect) {
+    let items: Vec<String> = app
+        .library
+        .as_ref()
+        .map(|library| {
+            library
+                .favorites
+                .iter()
+                .map(|f| format!("{} - {} ({})", f.track.artist, f.track.title, f.track.year))
+                .collect()
+        })
+        .unwrap_or_default();
+    let title = if app.library.is_some() {
+        format!("Favorites ({})", items.len())
+    } else {
+        String::from("Favorites (no library)")
+    };
     frame.render_stateful_widget(
-        List::new(app.get_countries_available())
-            .block(
-                get_block_style_selector(app.list_selected, app::SelectedList::Country)
-                    .title("Countries"),
-            )
+        List::new(items)
+            .block(Block::bordered().border_style(Color::Red).title(title))
             .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
-            .highlight_symbol(">>")
-            .repeat_highlight_symbol(true),
-        lists_layout[2],
-        app.country_state.borrow_mut(),
+            .highlight_symbol(">>"),
+        area,
+        &mut app.favorites_state,
     );
+}
 
-    match &app.play_state {
-        PlayState::Paused(_) | PlayState::Playing(_) => {
-            frame.render_widget(
-                Table::new(
-                    vec![
-                        Row::new(vec!["ddd", "dd"]),
-                        Row::new(vec!["dqsdqdsqd", "qsdqd"]),
-                    ],
-                    vec![Constraint::Fill(1), Constraint::Fill(1)],
-                )
-                .block(
-                    Block::bordered()
-                        .title("Title Infos")
-                        .title_alignment(Alignment::Left),
-                )
-                .style(Style::default()),
-                body_layout[1],
-            );
-        }
-        PlayState::Stopped => {}
-    }
+/// Rows of the track details table, plus its borders.
+const TRACK_DETAILS_HEIGHT: u16 = 13;
+/// Cells being about twice as high as wide, this keeps the cover square.
+const COVER_WIDTH: u16 = (TRACK_DETAILS_HEIGHT - 2) * 2 + 2;
 
-    /////////////////////////////////////
-    // footer
-    /////////////////////////////////////
-    frame.render_widget(Paragraph::new("shortcuts").centered(), master_layout[2])
+fn render_track_details(track: &radiooo::Track, liked: bool) -> Table<'_> {
+    // optional fields may be missing or sent as empty strings
+    let optional = |value: &Option<String>| match value.as_deref() {
+        Some(value) if !value.is_empty() => Cell::from(value.to_string()),
+        _ => Cell::from("unknown".dim()),
+    };
+    let rows = vec![
+        Row::new(vec![Cell::from("Title"), Cell::from(track.title.as_str())]),
+        Row::new(vec![
+            Cell::from("Artist"),
+            Cell::from(track.artist.as_str()),
+        ]),
+        Row::new(vec![Cell::from("Album"), optional(&track.album)]),
+        Row::new(vec![Cell::from("Songwriter"), optional(&track.songwriter)]),
+        Row::new(vec![Cell::from("Label"), optional(&track.label)]),
+        Row::new(vec![
+            Cell::from("Country"),
+            Cell::from(track.country.country().to_string()),
+        ]),
+        Row::new(vec![Cell::from("Year"), Cell::from(track.year.as_str())]),
+        Row::new(vec![
+            Cell::from("Decade"),
+            Cell::from(track.decade.to_string()),
+        ]),
+        Row::new(vec![
+            Cell::from("Length"),
+            Cell::from(format_duration(Duration::from_secs(track.length.into()))),
+        ]),
+        Row::new(vec![
+            Cell::from("Likes"),
+            Cell::from(track.likes.to_string()),
+        ]),
+        Row::new(vec![
+            Cell::from("Profile"),
+            Cell::from(track.profile_id.as_str()),
+        ]),
+    ];
+
+    Table::new(rows, vec![Constraint::Length(11), Constraint::Fill(1)])
+        .block(
+            Block::bordered()
+                .title(if liked {
+                    "Title Infos ♥"
+                } else {
+                    "Title Infos"
+                })
+                .title_alignment(Alignment::Left),
+        )
+        .style(Style::default())
 }
 
-fn render_play_state(p: &PlayState) -> Paragraph<'static> {
-    // .fg(Color::from_u32(233))
-    // .bg(Color::from_str("#FF5F87")
-    //     .expect("this is a valid color that should be recognised at runtime"))
+fn render_progress(position: Duration, duration: Duration) -> LineGauge<'static> {
+    let position = position.min(duration);
+    let ratio = if duration.is_zero() {
+        0.0
+    } else {
+        position.as_secs_f64() / duration.as_secs_f64()
+    };
+    LineGauge::default()
+        .block(Block::new().padding(Padding::horizontal(1)))
+        .label(format!(
+            "{} / {} (-{}) ",
+            format_duration(position),
+            format_duration(duration),
+            format_duration(duration - position)
+        ))
+        .filled_style(Style::new().fg(Color::Red))
+        .line_set(symbols::line::THICK)
+        .ratio(ratio.clamp(0.0, 1.0))
+}
 
-    match p {
-        PlayState::Paused(_) => Paragraph::new("Paused"),
-        PlayState::Playing(_) => Paragraph::new("Playing"),
-        PlayState::Stopped => Paragraph::new("Stopped"),
-    }
+/// Formats a duration as `m:ss`.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
 }
 
 fn render_volume_header_span(volume: u16, muted: bool) -> Line<'static> {
@@ -170,6 +514,23 @@ fn render_volume_header_span(volume: u16, muted: bool) -> Line<'static> {
     }
 }
 
+/// List entry prefixed by a mark when it is part of the multi selection.
+fn render_list_item(label: String, marked: bool) -> ListItem<'static> {
+    if marked {
+        ListItem::new(format!("● {}", label)).style(Style::new().green().bold())
+    } else {
+        ListItem::new(format!("  {}", label))
+    }
+}
+
+fn list_title(title: &str, marked: usize) -> String {
+    if marked == 0 {
+        title.to_string()
+    } else {
+        format!("{} ({} marked)", title, marked)
+    }
+}
+
 fn get_block_style_selector(
     current_state: app::SelectedList,
     target_block: app::SelectedList,
@@ -177,5 +538,111 @@ fn get_block_style_selector(
     if current_state == target_block {
         return Block::bordered().border_style(Color::Red);
     }
-    return Block::bordered();
+    Block::bordered()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cover::{Cover, GraphicsProtocol, Overlay};
+    use crate::player::FakePlayer;
+    use crate::radiooo::{tests::sample_track, RadioooClient};
+    use image::{DynamicImage, RgbImage};
+    use ratatui::backend::TestBackend;
+
+    fn render_to_string(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| render(app, frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_track_details_while_paused() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.play(sample_track());
+        app.playpause();
+        assert!(matches!(app.play_state, PlayState::Paused(_)));
+
+        let screen = render_to_string(&mut app);
+        assert!(screen.contains("Title Infos"));
+        assert!(screen.contains("Caballo Vapor"));
+        assert!(screen.contains("3:19"));
+        // the songwriter is an empty string
+        assert!(screen
+            .lines()
+            .any(|line| line.contains("Songwriter") && line.contains("unknown")));
+        assert!(screen.contains("0:00 / 3:19 (-3:19)"));
+    }
+
+    #[test]
+    fn test_help_popup_hides_the_cover() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        app.graphics = GraphicsProtocol::Kitty;
+        let track = sample_track();
+        app.play(track.clone());
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, image::Rgb([255, 0, 0])));
+        app.cover = Some(Cover::new(track.uuid, image));
+
+        let screen = Rect::new(0, 0, 120, 30);
+        let mut overlay = Overlay::default();
+        let mut frame = |app: &mut App| {
+            render_to_string(app);
+            let mut out = Vec::new();
+            overlay
+                .write(&mut out, screen, app.visible_cover())
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert!(frame(&mut app).contains("a=T"));
+        assert!(frame(&mut app).is_empty());
+
+        // deleted under the popup, placed again once it is closed
+        app.show_help = true;
+        let hidden = frame(&mut app);
+        assert!(hidden.contains("a=d") && !hidden.contains("a=T"));
+        assert!(frame(&mut app).is_empty());
+        app.show_help = false;
+        assert!(frame(&mut app).contains("a=T"));
+    }
+
+    #[test]
+    fn test_footer_and_help_popup() {
+        let client = RadioooClient::builder().build().unwrap();
+        let mut app = App::new(Box::new(FakePlayer::new()), client);
+        let footer = |app: &mut App| render_to_string(app).lines().last().unwrap().to_string();
+
+        let stopped = footer(&mut app);
+        assert!(stopped.contains("enter play") && stopped.contains("? help"));
+        assert!(!stopped.contains("next"));
+
+        app.play(sample_track());
+        assert!(footer(&mut app).contains("n next"));
+        app.toggle_favorites_view();
+        assert!(footer(&mut app).contains("esc back"));
+
+        // a notice replaces the shortcuts for a while, never the filter keys
+        app.notify("saved track.mp3");
+        assert!(footer(&mut app).contains("saved track.mp3"));
+        app.start_filter();
+        assert!(footer(&mut app).contains("esc cancel"));
+        app.filter = None;
+        let (notice, at) = app.notice.clone().unwrap();
+        app.notice = Some((notice, at - app::NOTICE_TTL));
+        assert!(footer(&mut app).contains("esc back"));
+
+        app.show_help = true;
+        let screen = render_to_string(&mut app);
+        assert!(screen.contains("Help"));
+        assert!(screen.contains("q, ctrl-c"));
+        assert!(screen.contains("Narrow the focused list to what is typed next"));
+        assert!(screen.contains("?/esc to close"));
+    }
 }